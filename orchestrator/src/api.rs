@@ -1,38 +1,163 @@
-use axum::{Json, Router, response::IntoResponse};
+use crate::auth::{self, Authenticator, Principal, Scope};
+use crate::backend::{self, ResourceUsage, SpawnRequest, WorkerBackend, WorkerRef};
+use crate::config::{
+    BudgetConfig, Config, ConfigHandle, ReconcileConfig, RecycleConfig, ReloadOutcome,
+    SchedulerConfig, SessionConfig, UnknownWorkers, WarmPoolConfig, WorkerConfig,
+};
+use crate::events::{EVENTS, EventFilter, EventKind, PoolEvent};
+use crate::logs::{LogLine, WORKER_LOGS};
+use crate::metrics::{self, METRICS};
+use crate::probes::{self, Probes};
+use crate::scheduler::{self, Candidate, Placement, PlacementRequest, Resources};
+use crate::shutdown::{LIFECYCLE, Lifecycle};
+use crate::telemetry::{self, RequestId};
+use axum::extract::Query;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router, middleware, routing::get};
 use axum::{extract::Path, extract::State, http::StatusCode};
+use futures::{Stream, StreamExt, stream};
 use reqwest::Client;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use restate_sdk::context::HeaderMap as RestateHeaderMap;
 use restate_sdk::prelude::*;
 use restate_sdk::serde::Json as RestateJson;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
-use std::net::TcpListener;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::process::Command;
-use utoipa::{OpenApi, ToSchema};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use utoipa_axum::{router::OpenApiRouter, routes};
 use utoipa_scalar::Scalar;
 use utoipa_scalar::Servable;
 
 #[derive(OpenApi)]
-#[openapi(paths(health, status, get_session, post_session, delete_session))]
+#[openapi(paths(
+    health,
+    status,
+    get_session,
+    post_session,
+    delete_session,
+    session_logs,
+    session_history,
+    events,
+    get_config,
+    put_config,
+    drain
+))]
 pub struct ApiDoc;
 
-#[derive(Default, Clone, Deserialize, Serialize, JsonSchema)]
+#[derive(Clone)]
 pub struct AppState {
     pub restate_base_url: String,
+    pub ingress_secret: Option<String>,
+    pub config: ConfigHandle,
+    pub lifecycle: &'static Lifecycle,
 }
 
-pub fn router(restate_base_url: String) -> Router {
-    let state = AppState { restate_base_url };
+impl AppState {
+    // Client for Restate ingress calls, carrying the request id and the caller's identity and
+    // signed with the shared secret when one is set
+    fn ingress_client(
+        &self,
+        request_id: &RequestId,
+        principal: &Principal,
+    ) -> Result<Client, (StatusCode, String)> {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+            headers.insert(telemetry::REQUEST_ID_HEADER, value);
+        }
+        let identity = [
+            (auth::SUBJECT_HEADER, principal.subject.as_deref()),
+            (auth::API_KEY_ID_HEADER, principal.api_key_id.as_deref()),
+            (auth::OWNER_HEADER, principal.owner()),
+        ];
+        for (name, value) in identity {
+            if let Some(value) = value.and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        telemetry::inject_context(&tracing::Span::current(), &mut headers);
+        if let Some(secret) = &self.ingress_secret {
+            let value = HeaderValue::from_str(secret).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Invalid ingress secret: {e}"),
+                )
+            })?;
+            headers.insert(auth::INGRESS_SECRET_HEADER, value);
+        }
+        Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to build ingress client: {e}"),
+                )
+            })
+    }
+}
+
+// Invokes a pool handler through the ingress on the orchestrator's own behalf, for the
+// background reconciliation and shutdown
+pub async fn call_pool(
+    client: &Client,
+    config: &Config,
+    handler: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let mut request = client.post(format!(
+        "{}/WorkerPoolService/pool/{}",
+        config.server.restate_ingress_url, handler
+    ));
+    if let Some(secret) = &config.auth.ingress_secret {
+        request = request.header(auth::INGRESS_SECRET_HEADER, secret);
+    }
+    let body = request.send().await?.error_for_status()?.bytes().await?;
+    // Handlers without a result answer with an empty body
+    if body.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
+
+pub fn router(config: ConfigHandle, authenticator: Arc<Authenticator>) -> Router {
+    let probes = Arc::new(Probes::new(config.clone()));
+    let current = config.get();
+    let state = AppState {
+        restate_base_url: current.server.restate_ingress_url.clone(),
+        ingress_secret: current.auth.ingress_secret.clone(),
+        config,
+        lifecycle: &LIFECYCLE,
+    };
     let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
         .routes(routes!(health))
         .routes(routes!(status))
         .routes(routes!(get_all_sessions))
         .routes(routes!(get_session, post_session, delete_session))
+        .routes(routes!(session_logs))
+        .routes(routes!(session_history))
+        .routes(routes!(events))
+        .routes(routes!(get_config, put_config))
+        .routes(routes!(drain))
         .with_state(state)
         .split_for_parts();
-    router.merge(Scalar::with_url("/", api))
+    // Docs stay public, only the API routes go through authentication
+    router
+        .layer(middleware::from_fn_with_state(
+            authenticator,
+            auth::authenticate,
+        ))
+        .layer(middleware::from_fn(telemetry::trace_http))
+        .layer(middleware::from_fn(metrics::track_http))
+        .route("/metrics", get(metrics::metrics))
+        .route("/healthz", get(probes::healthz))
+        .route("/readyz", get(probes::readyz).with_state(probes))
+        .merge(Scalar::with_url("/", api))
 }
 #[derive(Default, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Session {
@@ -40,40 +165,871 @@ pub struct Session {
     available: bool,
     worker_id: String,
     user: String,
+    // Set by reconciliation when the session's worker was lost
+    #[serde(default)]
+    failure: Option<String>,
+    // Requested at creation, counted against its worker's resources
+    #[serde(default)]
+    resources: Resources,
+    // Unix seconds, what the TTL is measured from. 0 for sessions recorded before it was,
+    // those count as expired.
+    #[serde(default)]
+    created_at: i64,
+}
+
+impl Session {
+    // Its worker may still serve other sessions, an ended session must not reach it
+    fn check_alive(&self) -> Result<(), TerminalError> {
+        if let Some(reason) = &self.failure {
+            return Err(TerminalError::new(format!("Session failed: {}", reason)));
+        }
+        if !self.available {
+            return Err(TerminalError::new_with_code(404, "Session not found"));
+        }
+        Ok(())
+    }
+
+    fn expired(&self, now: i64, ttl_secs: u64) -> bool {
+        now - self.created_at >= ttl_secs as i64
+    }
 }
 #[derive(Default, Clone, Deserialize, Serialize)]
 pub struct Worker {
     id: String,
     port: Option<u16>,
     available: bool,
+    // Bearer token handed to the worker at spawn, empty for workers spawned before tokens
+    #[serde(default)]
+    token: String,
+    // Backend specific id (pid, container, pod), unset for workers spawned before backends
+    #[serde(default)]
+    handle: Option<String>,
+    // First sample over the memory budget, cleared once back under it
+    #[serde(default)]
+    over_budget_since: Option<i64>,
+    // Unix seconds, 0 for workers started before it was recorded
+    #[serde(default)]
+    started_at: i64,
+    #[serde(default)]
+    sessions_served: u64,
+    // Pool generation the worker was started in, it moves on with every retirement
+    #[serde(default)]
+    generation: u64,
+    // Why the worker retires once its sessions end, it takes no new ones meanwhile
+    #[serde(default)]
+    retiring: Option<String>,
+    // Sessions it serves at once, 0 for workers recorded before capacities which held one
+    #[serde(default)]
+    capacity: u64,
+    // Where the backend put it, unset for backends with a single node
+    #[serde(default)]
+    node: Option<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    // What it offers to its sessions' resource requests
+    #[serde(default)]
+    resources: Resources,
+}
+
+impl Worker {
+    fn capacity(&self) -> u64 {
+        self.capacity.max(1)
+    }
+
+    fn backend_ref(&self) -> WorkerRef {
+        WorkerRef {
+            id: self.id.clone(),
+            port: self.port,
+            token: self.token.clone(),
+            handle: self.handle.clone(),
+        }
+    }
 }
 #[derive(Default, Deserialize, Serialize)]
 pub struct Pool {
     session_list: Vec<Session>,
     worker_list: Vec<Worker>,
+    #[serde(default)]
+    generation: u64,
+    // Workers that crashed or retired, oldest first, until a new worker takes each one's place
+    #[serde(default)]
+    replaced: Vec<String>,
+}
+
+// Beyond that many workers gone without a successor, the oldest are no longer announced
+const PENDING_REPLACEMENTS: usize = 64;
+
+impl Pool {
+    // Failed sessions linger in the list without being available until the next reaper pass
+    fn active_sessions(&self, worker: &Worker) -> u64 {
+        self.session_list
+            .iter()
+            .filter(|s| s.worker_id == worker.id && s.available)
+            .count() as u64
+    }
+
+    // Mirrors the pool in the gauges, called whenever a handler writes it back
+    fn observe(&self) {
+        let sessions = self.session_list.iter().filter(|s| s.available).count();
+        METRICS.observe_pool(sessions, self.worker_list.len());
+    }
+
+    fn has_session(&self, worker: &Worker) -> bool {
+        self.active_sessions(worker) > 0
+    }
+
+    // The workers as the scheduler sees them, in worker_list order
+    fn candidates(&self, recycle: &RecycleConfig) -> Vec<Candidate<'_>> {
+        self.worker_list
+            .iter()
+            .map(|w| {
+                let mut requested = Resources::default();
+                for session in self
+                    .session_list
+                    .iter()
+                    .filter(|s| s.worker_id == w.id && s.available)
+                {
+                    requested.add(&session.resources);
+                }
+                Candidate {
+                    worker_id: &w.id,
+                    node: w.node.as_deref(),
+                    labels: &w.labels,
+                    accepting: w.available
+                        && w.retiring.is_none()
+                        && (recycle.max_sessions == 0 || w.sessions_served < recycle.max_sessions),
+                    sessions: self.active_sessions(w),
+                    capacity: w.capacity(),
+                    offered: &w.resources,
+                    requested,
+                }
+            })
+            .collect()
+    }
+
+    // Ready warm workers waiting for a session
+    fn idle_workers(&self) -> usize {
+        self.worker_list
+            .iter()
+            .filter(|w| w.available && !self.has_session(w))
+            .count()
+    }
+
+    fn worker_gone(&mut self, worker_id: &str) {
+        self.replaced.push(worker_id.to_string());
+        if self.replaced.len() > PENDING_REPLACEMENTS {
+            self.replaced.remove(0);
+        }
+    }
+
+    // The crashed or retired worker a newly started one stands in for
+    fn take_replaced(&mut self) -> Option<String> {
+        (!self.replaced.is_empty()).then(|| self.replaced.remove(0))
+    }
+
+    // Idle plus still warming up, what the warm pool size is measured against
+    fn warm_workers(&self) -> usize {
+        self.worker_list
+            .iter()
+            .filter(|w| !self.has_session(w))
+            .count()
+    }
 }
 #[derive(Default, Clone, Deserialize, Serialize, JsonSchema, ToSchema)]
 pub struct CreateSessionResponse {
     id: String,
     created_at: i64,
     data: Data,
+    // Set in listings when the session's worker couldn't be asked, the rest is the pool's record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 #[derive(Default, Clone, Deserialize, Serialize, JsonSchema, ToSchema)]
 pub struct SessionStatusResponse {
     session_id: String,
     available: bool,
+    // Sampled by the orchestrator, workers don't report it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<ResourceUsage>,
+}
+// A warm worker's readiness probe, repeated until it answers or runs out of attempts
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct WarmCheck {
+    worker_id: String,
+    attempt: u64,
+}
+
+// Time between readiness probes of a warming worker
+const WARM_PROBE_INTERVAL: Duration = Duration::from_millis(500);
+
+// What one reconciliation pass changed
+#[derive(Default, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ReconcileReport {
+    pub dead_workers: usize,
+    pub failed_sessions: usize,
+    pub adopted: usize,
+    pub killed: usize,
 }
 #[derive(Default, Clone, Deserialize, Serialize, JsonSchema, ToSchema)]
 pub struct Data {
     pub user: String,
 }
+// Body of POST /session, the placement constraints are optional
+#[derive(Default, Clone, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct CreateSessionRequest {
+    pub user: String,
+    #[serde(flatten)]
+    pub placement: PlacementRequest,
+}
+// Restate service implementation, the persisted state lives in `Pool`
+pub struct WorkerPool {
+    ingress_secret: Option<String>,
+    // Read per invocation so reloads apply to the next call
+    config: ConfigHandle,
+    backend: Arc<dyn WorkerBackend>,
+}
+
+// The part of the config the pool's handlers act on. Handlers read it through `ctx.run`, so a
+// replay decides with the values the original run saw even when a reload changed them since.
+#[derive(Clone, Deserialize, Serialize)]
+struct Settings {
+    session: SessionConfig,
+    worker: WorkerConfig,
+    warm_pool: WarmPoolConfig,
+    recycle: RecycleConfig,
+    budget: BudgetConfig,
+    scheduler: SchedulerConfig,
+    reconcile: ReconcileConfig,
+}
+
+impl WorkerPool {
+    pub fn new(config: ConfigHandle, backend: Arc<dyn WorkerBackend>) -> Self {
+        WorkerPool {
+            ingress_secret: config.get().auth.ingress_secret.clone(),
+            config,
+            backend,
+        }
+    }
+
+    async fn settings(&self, ctx: &ObjectContext<'_>) -> Result<Settings, HandlerError> {
+        let config = self.config.get();
+        let RestateJson(settings) = ctx
+            .run(|| async move {
+                Ok(RestateJson(Settings {
+                    session: config.session.clone(),
+                    worker: config.worker.clone(),
+                    warm_pool: config.warm_pool.clone(),
+                    recycle: config.recycle.clone(),
+                    budget: config.budget.clone(),
+                    scheduler: config.scheduler.clone(),
+                    reconcile: config.reconcile.clone(),
+                }))
+            })
+            .await?;
+        Ok(settings)
+    }
+
+    // Base URL of the worker's steel-browser API, journaled since backends may look the
+    // address up remotely
+    async fn endpoint(
+        &self,
+        ctx: &ObjectContext<'_>,
+        worker: &Worker,
+    ) -> Result<String, TerminalError> {
+        let backend = self.backend.clone();
+        let worker = worker.backend_ref();
+        ctx.run(|| async move {
+            backend
+                .endpoint(&worker)
+                .await
+                .map_err(|e| TerminalError::new(format!("{e:#}")).into())
+        })
+        .await
+    }
+
+    // The session as its worker reports it
+    async fn fetch_session(
+        &self,
+        ctx: &ObjectContext<'_>,
+        pool: &Pool,
+        session: &Session,
+    ) -> Result<CreateSessionResponse, TerminalError> {
+        let worker = pool
+            .worker_list
+            .iter()
+            .find(|w| w.id == session.worker_id)
+            .ok_or(TerminalError::new(
+                "Error fetching session_worker from worker_list",
+            ))?;
+        let base = self.endpoint(ctx, worker).await?;
+        let span = side_effect_span("get_session", Some(&session.id), worker);
+
+        let client = worker_client(&worker.token, &span)?;
+        let session_id = session.id.clone();
+        let body: String = ctx
+            .run(move || async move {
+                let response = client
+                    .get(format!("{}/sessions/{}", base, session_id))
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        TerminalError::new(format!(
+                            "Failed to send get_all_sessions request: {}",
+                            e
+                        ))
+                    })?;
+
+                let body = response.text().await.map_err(|e| {
+                    TerminalError::new(format!(
+                        "Failed to get_all_sessions health response body: {}",
+                        e
+                    ))
+                })?;
+
+                Ok(body)
+            })
+            .instrument(span)
+            .await?;
+        serde_json::from_str(&body)
+            .map_err(|e| TerminalError::new(format!("Invalid session JSON: {}", e)))
+    }
+
+    async fn stop_worker(
+        &self,
+        ctx: &ObjectContext<'_>,
+        worker: &Worker,
+    ) -> Result<(), TerminalError> {
+        let backend = self.backend.clone();
+        let worker = worker.backend_ref();
+        let worker_id = worker.id.clone();
+        let span = tracing::info_span!("side_effect", op = "stop_worker", worker_id = %worker.id);
+        ctx.run(|| async move {
+            backend
+                .stop(&worker)
+                .await
+                .map_err(|e| TerminalError::new(format!("Failed to stop worker: {e:#}")).into())
+        })
+        .instrument(span)
+        .await
+        .inspect(|_| METRICS.forget_worker(&worker_id))
+    }
+
+    async fn usage(
+        &self,
+        ctx: &ObjectContext<'_>,
+        worker: &Worker,
+    ) -> Result<Option<ResourceUsage>, TerminalError> {
+        let backend = self.backend.clone();
+        let worker = worker.backend_ref();
+        let RestateJson(usage) = ctx
+            .run(|| async move { Ok(RestateJson(backend.usage(&worker).await)) })
+            .await?;
+        Ok(usage)
+    }
+
+    async fn exit_reason(
+        &self,
+        ctx: &ObjectContext<'_>,
+        worker: &Worker,
+    ) -> Result<Option<String>, TerminalError> {
+        let backend = self.backend.clone();
+        let worker = worker.backend_ref();
+        ctx.run(|| async move { Ok(backend.exit_reason(&worker).await) })
+            .await
+    }
+
+    // Rejects invocations that don't carry the shared ingress secret
+    fn verify_caller(&self, headers: &RestateHeaderMap) -> Result<(), TerminalError> {
+        let Some(secret) = &self.ingress_secret else {
+            return Ok(());
+        };
+        match headers.get(auth::INGRESS_SECRET_HEADER) {
+            Some(given) if auth::constant_time_eq(given.as_bytes(), secret.as_bytes()) => Ok(()),
+            _ => Err(TerminalError::new_with_code(
+                401,
+                "Invalid or missing ingress secret",
+            )),
+        }
+    }
+
+    // Stops a worker under the recycle policy, a replacement is warmed up when the warm pool
+    // is enabled. The caller moves the pool generation on.
+    async fn retire(
+        &self,
+        ctx: &ObjectContext<'_>,
+        settings: &Settings,
+        worker: &Worker,
+        reason: &str,
+    ) -> Result<(), HandlerError> {
+        tracing::info!(
+            worker_id = %worker.id,
+            reason,
+            sessions_served = worker.sessions_served,
+            "retiring worker"
+        );
+        if let Err(e) = self.stop_worker(ctx, worker).await {
+            tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+        }
+        WORKER_LOGS.remove(&worker.id);
+        count(ctx, || {
+            METRICS
+                .worker_retirements
+                .with_label_values(&[reason])
+                .inc()
+        })
+        .await?;
+        publish(
+            ctx,
+            PoolEvent::new(EventKind::WorkerRetired)
+                .worker(&worker.id)
+                .reason(reason),
+        )
+        .await?;
+        self.schedule_worker_history_prune(ctx, settings, &worker.id);
+        if settings.warm_pool.size > 0 {
+            self.schedule_replenish(ctx);
+        }
+        Ok(())
+    }
+
+    // Frees the slot of a session that ended, the caller has already taken the session out
+    // of the active ones. A worker that reached a recycle limit drains and retires with its
+    // last session, any other stays in the pool.
+    async fn release_slot(
+        &self,
+        ctx: &ObjectContext<'_>,
+        settings: &Settings,
+        pool: &mut Pool,
+        worker_id: &str,
+    ) -> Result<(), HandlerError> {
+        let Some(index) = pool.worker_list.iter().position(|w| w.id == worker_id) else {
+            return Ok(());
+        };
+        let now = ctx.run(|| async { Ok(unix_now()) }).await?;
+        let busy = pool.has_session(&pool.worker_list[index]);
+        let worker = &mut pool.worker_list[index];
+        let reason = worker
+            .retiring
+            .clone()
+            .or_else(|| retire_reason(worker, &settings.recycle, None, now).map(str::to_string));
+        match reason {
+            Some(reason) if busy => {
+                worker.available = false;
+                worker.retiring = Some(reason);
+            }
+            Some(reason) => {
+                let worker = pool.worker_list.remove(index);
+                self.retire(ctx, settings, &worker, &reason).await?;
+                pool.worker_gone(&worker.id);
+                pool.generation += 1;
+            }
+            None => worker.available = true,
+        }
+        Ok(())
+    }
+
+    // Probes an unknown worker and reads back the session it is serving. None when it can't
+    // be adopted, Some(None) for a healthy worker without a session.
+    async fn adopt(
+        &self,
+        ctx: &ObjectContext<'_>,
+        worker: &Worker,
+    ) -> Result<Option<Option<Session>>, HandlerError> {
+        let span = side_effect_span("health", None, worker);
+        let backend = self.backend.clone();
+        let worker_ref = worker.backend_ref();
+        let alive: bool = ctx
+            .run(move || async move { Ok(backend.probe(&worker_ref).await) })
+            .instrument(span)
+            .await?;
+        if !alive {
+            return Ok(None);
+        }
+        let Ok(base) = self.endpoint(ctx, worker).await else {
+            return Ok(None);
+        };
+        let span = side_effect_span("status", None, worker);
+        let client = worker_client(&worker.token, &span)?;
+        let body: Option<String> = ctx
+            .run(move || async move {
+                let response = client.get(format!("{}/status", base)).send().await;
+                match response {
+                    Ok(response) if response.status().is_success() => {
+                        Ok(response.text().await.ok())
+                    }
+                    _ => Ok(None),
+                }
+            })
+            .instrument(span)
+            .await?;
+        let Some(status) =
+            body.and_then(|b| serde_json::from_str::<CreateSessionResponse>(&b).ok())
+        else {
+            return Ok(None);
+        };
+        if status.id.is_empty() {
+            return Ok(Some(None));
+        }
+        Ok(Some(Some(Session {
+            id: status.id,
+            available: true,
+            worker_id: worker.id.clone(),
+            user: status.data.user,
+            failure: None,
+            // Unknown, it counts as requesting nothing
+            resources: Resources::default(),
+            created_at: status.created_at,
+        })))
+    }
+
+    // Drops the session's history once the retention period has passed, the delayed call is
+    // signed like any other ingress call
+    fn schedule_history_prune(
+        &self,
+        ctx: &ObjectContext<'_>,
+        settings: &Settings,
+        session_id: &str,
+    ) {
+        let mut request = ctx
+            .object_client::<WorkerPoolServiceClient>(ctx.key())
+            .prune_history(session_id.to_string());
+        if let Some(secret) = &self.ingress_secret {
+            request = request.header(auth::INGRESS_SECRET_HEADER.to_string(), secret.clone());
+        }
+        request.send_after(settings.session.history_retention());
+    }
+
+    // The same for a worker that left the pool, its trail outlives the last of its sessions.
+    // Its captured output goes with it, so a crash can still be looked into until then.
+    fn schedule_worker_history_prune(
+        &self,
+        ctx: &ObjectContext<'_>,
+        settings: &Settings,
+        worker_id: &str,
+    ) {
+        let mut request = ctx
+            .object_client::<WorkerPoolServiceClient>(ctx.key())
+            .prune_worker_history(worker_id.to_string());
+        if let Some(secret) = &self.ingress_secret {
+            request = request.header(auth::INGRESS_SECRET_HEADER.to_string(), secret.clone());
+        }
+        request.send_after(settings.session.history_retention());
+    }
+
+    fn schedule_replenish(&self, ctx: &ObjectContext<'_>) {
+        let mut request = ctx
+            .object_client::<WorkerPoolServiceClient>(ctx.key())
+            .replenish_warm_pool();
+        if let Some(secret) = &self.ingress_secret {
+            request = request.header(auth::INGRESS_SECRET_HEADER.to_string(), secret.clone());
+        }
+        request.send();
+    }
+
+    fn schedule_warm_check(&self, ctx: &ObjectContext<'_>, check: WarmCheck, delay: Duration) {
+        let mut request = ctx
+            .object_client::<WorkerPoolServiceClient>(ctx.key())
+            .check_warm_worker(RestateJson(check));
+        if let Some(secret) = &self.ingress_secret {
+            request = request.header(auth::INGRESS_SECRET_HEADER.to_string(), secret.clone());
+        }
+        request.send_after(delay);
+    }
+
+    // Starts a worker through the backend under a fresh id and token. It is available, the
+    // caller decides whether it is idle.
+    async fn start_worker(
+        &self,
+        ctx: &mut ObjectContext<'_>,
+        settings: &Settings,
+        user: &str,
+        session: Option<&PoolEvent>,
+    ) -> Result<Worker, HandlerError> {
+        let worker_id = ctx.rand_uuid().to_string();
+        // A bearer secret, so drawn from the OS CSPRNG rather than the seeded ctx.rand
+        let token = ctx
+            .run(|| async { Ok(uuid::Uuid::new_v4().simple().to_string()) })
+            .await?;
+        let span = tracing::info_span!(
+            "side_effect",
+            op = "start_worker",
+            backend = self.backend.name(),
+            worker_id = %worker_id,
+        );
+        let request = SpawnRequest {
+            worker_id: worker_id.clone(),
+            token: token.clone(),
+            user: user.to_string(),
+        };
+        let backend = self.backend.clone();
+        let spawned = ctx
+            .run(|| async move {
+                backend
+                    .spawn(&request)
+                    .await
+                    .map(RestateJson)
+                    .map_err(|e| TerminalError::new(format!("{e:#}")).into())
+            })
+            .instrument(span)
+            .await;
+        let RestateJson(spawned) = spawn_failed(ctx, "process_start", session, spawned).await?;
+        let started_at = ctx.run(|| async { Ok(unix_now()) }).await?;
+        Ok(Worker {
+            id: worker_id,
+            port: spawned.port,
+            available: true,
+            token,
+            handle: spawned.handle,
+            over_budget_since: None,
+            started_at,
+            sessions_served: 0,
+            generation: 0,
+            retiring: None,
+            capacity: settings.worker.session_capacity,
+            node: spawned.node,
+            labels: settings.scheduler.worker_labels.clone(),
+            resources: settings.scheduler.worker_resources.clone(),
+        })
+    }
+}
+
+// Callers restricted to their own sessions get a 404 for anyone else's, the same as for a
+// session that doesn't exist
+fn check_owner(headers: &RestateHeaderMap, user: Option<&str>) -> Result<(), TerminalError> {
+    match headers.get(auth::OWNER_HEADER) {
+        Some(owner) if Some(owner.as_str()) != user => Err(TerminalError::new_with_code(
+            404,
+            "Error fetching session from session_list",
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Workers only accept calls carrying the token they were spawned with, `span` is the side
+// effect the calls belong to and is propagated as the W3C trace parent
+fn worker_client(token: &str, span: &tracing::Span) -> Result<Client, TerminalError> {
+    backend::worker_client(token, span)
+        .map_err(|e| TerminalError::new(format!("Failed to build worker client: {}", e)))
+}
+
+// Annotates a `ctx.run` side effect, the enclosing handler span carries the request id
+fn side_effect_span(op: &'static str, session_id: Option<&str>, worker: &Worker) -> tracing::Span {
+    tracing::info_span!(
+        "side_effect",
+        op,
+        session_id = session_id.unwrap_or_default(),
+        worker_id = %worker.id,
+        port = worker.port,
+    )
+}
+
+fn history_key(session_id: &str) -> String {
+    format!("history:{}", session_id)
+}
+
+fn worker_history_key(worker_id: &str) -> String {
+    format!("history:worker:{}", worker_id)
+}
+
+async fn read_history(
+    ctx: &ObjectContext<'_>,
+    key: &str,
+) -> Result<Option<Vec<PoolEvent>>, HandlerError> {
+    match ctx.get::<Vec<u8>>(key).await? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+// Appends to the session's audit trail, kept in its own state key so it outlives the session.
+// Worker events without a session go to the worker's trail, merged into its sessions' history
+// when read.
+async fn record_history(ctx: &ObjectContext<'_>, event: PoolEvent) -> Result<(), HandlerError> {
+    let key = match (&event.session_id, &event.worker_id) {
+        (Some(session_id), _) => history_key(session_id),
+        (None, Some(worker_id)) => worker_history_key(worker_id),
+        (None, None) => return Ok(()),
+    };
+    let mut history = read_history(ctx, &key).await?.unwrap_or_default();
+    history.push(event);
+    ctx.set(&key, serde_json::to_vec(&history)?);
+    Ok(())
+}
+
+// Published through `ctx.run` so a replayed handler doesn't announce the same transition twice.
+// Returns the event stamped with its journaled publish time.
+async fn publish(ctx: &ObjectContext<'_>, event: PoolEvent) -> Result<PoolEvent, HandlerError> {
+    let RestateJson(event) = ctx
+        .run(|| async move { Ok(RestateJson(EVENTS.publish(event))) })
+        .await?;
+    record_history(ctx, event.clone()).await?;
+    Ok(event)
+}
+
+// Announces a newly started worker as the successor of one that crashed or retired, if any
+async fn announce_replacement(
+    ctx: &ObjectContext<'_>,
+    pool: &mut Pool,
+    worker: &Worker,
+    session: Option<&PoolEvent>,
+) -> Result<(), HandlerError> {
+    let Some(replaced) = pool.take_replaced() else {
+        return Ok(());
+    };
+    let mut event = PoolEvent::new(EventKind::WorkerRestarted)
+        .worker(&worker.id)
+        .reason(&format!("replaces {}", replaced));
+    if let Some(session) = session {
+        event.session_id = session.session_id.clone();
+        event.user = session.user.clone();
+    }
+    publish(ctx, event).await?;
+    Ok(())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+// Journaled so a handler replayed after the drain began still finishes what it started
+async fn draining(ctx: &ObjectContext<'_>) -> Result<bool, TerminalError> {
+    ctx.run(|| async { Ok(LIFECYCLE.is_draining()) }).await
+}
+
+// Counters only move in a side effect, a replayed handler reads the journal instead of
+// counting again
+async fn count(ctx: &ObjectContext<'_>, inc: impl FnOnce() + Send) -> Result<(), TerminalError> {
+    ctx.run(|| async move {
+        inc();
+        Ok(())
+    })
+    .await
+}
+
+// Counts a failed session spawn and ends the session's history with it, when the spawn was
+// for a session
+async fn abandon_spawn(
+    ctx: &ObjectContext<'_>,
+    reason: &'static str,
+    session: Option<&PoolEvent>,
+) -> Result<(), HandlerError> {
+    count(ctx, || METRICS.spawn_failed(reason)).await?;
+    if let Some(session) = session {
+        let mut failed = PoolEvent::new(EventKind::SessionFailed).reason(reason);
+        failed.session_id = session.session_id.clone();
+        failed.user = session.user.clone();
+        publish(ctx, failed).await?;
+    }
+    Ok(())
+}
+
+// The same before the handler fails with `result`'s error
+async fn spawn_failed<T>(
+    ctx: &ObjectContext<'_>,
+    reason: &'static str,
+    session: Option<&PoolEvent>,
+    result: Result<T, TerminalError>,
+) -> Result<T, HandlerError> {
+    if result.is_err() {
+        abandon_spawn(ctx, reason, session).await?;
+    }
+    Ok(result?)
+}
+
+// Which recycle limit the worker reached, None while it may take more sessions
+fn retire_reason(
+    worker: &Worker,
+    recycle: &RecycleConfig,
+    usage: Option<&ResourceUsage>,
+    now: i64,
+) -> Option<&'static str> {
+    if recycle.max_sessions > 0 && worker.sessions_served >= recycle.max_sessions {
+        return Some("max_sessions");
+    }
+    if let Some(max_age) = recycle.max_age_secs
+        && worker.started_at > 0
+        && now - worker.started_at >= max_age as i64
+    {
+        return Some("max_age");
+    }
+    if let (Some(limit), Some(usage)) = (recycle.memory_bytes, usage)
+        && usage.rss_bytes > limit
+    {
+        return Some("memory");
+    }
+    None
+}
+
+// Tracks how long the worker has been over the soft memory budget, true once that outlasted
+// the grace period
+fn budget_exceeded(
+    worker: &mut Worker,
+    usage: Option<&ResourceUsage>,
+    budget: &BudgetConfig,
+    now: i64,
+) -> bool {
+    let over = match (budget.memory_bytes, usage) {
+        (Some(limit), Some(usage)) => usage.rss_bytes > limit,
+        _ => false,
+    };
+    if !over {
+        worker.over_budget_since = None;
+        return false;
+    }
+    let since = *worker.over_budget_since.get_or_insert(now);
+    now - since >= budget.grace_secs as i64
+}
+
+// Puts the sessions of a dead worker in their terminal state, returns how many
+async fn fail_sessions(
+    ctx: &ObjectContext<'_>,
+    sessions: &mut [Session],
+    worker_id: &str,
+    reason: &str,
+) -> Result<usize, HandlerError> {
+    let mut failed = 0;
+    for session in sessions
+        .iter_mut()
+        .filter(|s| s.worker_id == worker_id && s.failure.is_none())
+    {
+        session.available = false;
+        session.failure = Some(reason.to_string());
+        failed += 1;
+        publish(
+            ctx,
+            PoolEvent::new(EventKind::SessionFailed)
+                .session(&session.id)
+                .worker(worker_id)
+                .user(&session.user)
+                .reason(reason),
+        )
+        .await?;
+    }
+    Ok(failed)
+}
+
 // Restate service definition
 #[restate_sdk::object]
 pub trait WorkerPoolService {
     async fn poll_stale_sessions() -> Result<(), HandlerError>;
     async fn poll_stale_workers() -> Result<(), HandlerError>;
-    async fn spawn_worker(user: String)
-    -> Result<RestateJson<CreateSessionResponse>, HandlerError>;
+    async fn reconcile() -> Result<RestateJson<ReconcileReport>, HandlerError>;
+    async fn terminate_workers() -> Result<u64, HandlerError>;
+    async fn replenish_warm_pool() -> Result<(), HandlerError>;
+    async fn check_warm_worker(check: RestateJson<WarmCheck>) -> Result<(), HandlerError>;
+    async fn spawn_worker(
+        request: RestateJson<CreateSessionRequest>,
+    ) -> Result<RestateJson<CreateSessionResponse>, HandlerError>;
     async fn health_check(session_id: String) -> Result<String, HandlerError>;
     async fn status_check(
         session_id: String,
@@ -83,15 +1039,24 @@ pub trait WorkerPoolService {
     ) -> Result<RestateJson<CreateSessionResponse>, HandlerError>;
     async fn get_all_sessions() -> Result<RestateJson<Vec<CreateSessionResponse>>, HandlerError>;
     async fn delete_session(session_id: String) -> Result<String, HandlerError>;
+    async fn resolve_worker(session_id: String) -> Result<String, HandlerError>;
+    async fn get_history(session_id: String) -> Result<RestateJson<Vec<PoolEvent>>, HandlerError>;
+    async fn prune_history(session_id: String) -> Result<(), HandlerError>;
+    async fn prune_worker_history(worker_id: String) -> Result<(), HandlerError>;
 }
 // Restate service implementation
-impl WorkerPoolService for Pool {
+impl WorkerPoolService for WorkerPool {
     // Helps with inducing TTL based session timeouts
+    #[tracing::instrument(
+        skip_all,
+        fields(request_id = telemetry::restate_request_id(ctx.headers()))
+    )]
     async fn poll_stale_sessions(&self, ctx: ObjectContext<'_>) -> Result<(), HandlerError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let now = ctx.run(|| async { Ok(unix_now()) }).await?;
+        let settings = self.settings(&ctx).await?;
+        let ttl_secs = settings.session.ttl_secs;
 
         let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
@@ -99,93 +1064,495 @@ impl WorkerPoolService for Pool {
         };
 
         let mut remaining_sessions = Vec::new();
+        let mut released = Vec::new();
 
-        for session in pool.session_list.into_iter() {
-            let age = now - 234;
-
-            if age < 60 {
+        for session in std::mem::take(&mut pool.session_list) {
+            // Failed sessions only linger until the next pass, their worker is gone
+            if !session.available {
+                continue;
+            }
+            if !session.expired(now, ttl_secs) {
                 remaining_sessions.push(session);
                 continue;
             }
 
             // stale session → delete remotely
+            tracing::info!(
+                session_id = %session.id,
+                worker_id = %session.worker_id,
+                "reaping expired session"
+            );
+            count(&ctx, || {
+                METRICS
+                    .reaper_deletions
+                    .with_label_values(&["ttl_expired"])
+                    .inc()
+            })
+            .await?;
+            publish(
+                &ctx,
+                PoolEvent::new(EventKind::SessionExpired)
+                    .session(&session.id)
+                    .worker(&session.worker_id)
+                    .user(&session.user)
+                    .reason("ttl_expired"),
+            )
+            .await?;
+            self.schedule_history_prune(&ctx, &settings, &session.id);
             let worker = pool
                 .worker_list
-                .iter_mut()
-                .find(|w| w.id == session.worker_id);
+                .iter()
+                .find(|w| w.id == session.worker_id)
+                .cloned();
 
             if let Some(worker) = worker {
-                if let Some(port) = worker.port {
+                if let Ok(base) = self.endpoint(&ctx, &worker).await {
                     let session_id = session.id.clone();
+                    let token = worker.token.clone();
+                    let span = side_effect_span("delete_session", Some(&session_id), &worker);
 
                     ctx.run(move || async move {
-                        let client = Client::new();
+                        let client = worker_client(&token, &tracing::Span::current())?;
                         let _ = client
-                            .delete(format!("http://localhost:{}/sessions/{}", port, session_id))
+                            .delete(format!("{}/sessions/{}", base, session_id))
                             .send()
                             .await;
                         Ok(())
                     })
+                    .instrument(span)
                     .await?;
                 }
-
-                worker.available = true;
+                released.push(worker.id);
             }
         }
 
         pool.session_list = remaining_sessions;
+        for worker_id in released {
+            self.release_slot(&ctx, &settings, &mut pool, &worker_id)
+                .await?;
+        }
 
         ctx.set("pool_state", serde_json::to_vec(&pool)?);
+        pool.observe();
 
         Ok(())
     }
+    #[tracing::instrument(
+        skip_all,
+        fields(request_id = telemetry::restate_request_id(ctx.headers()))
+    )]
     async fn poll_stale_workers(&self, ctx: ObjectContext<'_>) -> Result<(), HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
         let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => return Ok(()),
         };
+        let now = ctx.run(|| async { Ok(unix_now()) }).await?;
+        let settings = self.settings(&ctx).await?;
+        let budget = &settings.budget;
+        let recycle = &settings.recycle;
 
         let mut healthy_workers = Vec::new();
 
-        for mut worker in pool.worker_list.into_iter() {
-            let client = Client::new();
-            let health_status: String = ctx
-                .run(move || async move {
-                    let response = client
-                        .get(format!(
-                            "http://localhost:{}/health",
-                            worker.port.unwrap_or_default().to_string()
-                        ))
-                        .send()
-                        .await
-                        .map_err(|e| {
-                            TerminalError::new(format!("Failed to send health request: {}", e))
-                        })?;
-
-                    let body = response.text().await.map_err(|e| {
-                        TerminalError::new(format!("Failed to read health response body: {}", e))
-                    })?;
-
-                    Ok(body)
+        for mut worker in std::mem::take(&mut pool.worker_list) {
+            let span = side_effect_span("health", None, &worker);
+            let backend = self.backend.clone();
+            let worker_ref = worker.backend_ref();
+            let healthy: bool = ctx
+                .run(move || async move { Ok(backend.probe(&worker_ref).await) })
+                .instrument(span)
+                .await?;
+            if healthy {
+                let usage = self.usage(&ctx, &worker).await?;
+                if let Some(usage) = &usage {
+                    METRICS.observe_usage(&worker.id, usage);
+                }
+                if !budget_exceeded(&mut worker, usage.as_ref(), budget, now) {
+                    let busy = pool
+                        .session_list
+                        .iter()
+                        .any(|s| s.worker_id == worker.id && s.available);
+                    let reason = retire_reason(&worker, recycle, usage.as_ref(), now);
+                    match reason {
+                        // Drains, release_slot retires it with its last session
+                        Some(reason) if busy => {
+                            worker.available = false;
+                            worker.retiring.get_or_insert_with(|| reason.to_string());
+                        }
+                        Some(reason) => {
+                            self.retire(&ctx, &settings, &worker, reason).await?;
+                            pool.worker_gone(&worker.id);
+                            pool.generation += 1;
+                            continue;
+                        }
+                        None => {}
+                    }
+                    healthy_workers.push(worker);
+                    continue;
+                }
+                tracing::warn!(worker_id = %worker.id, "killing worker over its memory budget");
+                if let Err(e) = self.stop_worker(&ctx, &worker).await {
+                    tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+                }
+                fail_sessions(
+                    &ctx,
+                    &mut pool.session_list,
+                    &worker.id,
+                    "memory_budget_exceeded",
+                )
+                .await?;
+                self.schedule_worker_history_prune(&ctx, &settings, &worker.id);
+                pool.worker_gone(&worker.id);
+                count(&ctx, || {
+                    METRICS
+                        .reaper_deletions
+                        .with_label_values(&["memory_budget"])
+                        .inc()
+                })
+                .await?;
+            } else {
+                tracing::warn!(worker_id = %worker.id, "dropping unhealthy worker");
+                let exit_reason = self.exit_reason(&ctx, &worker).await?;
+                if let Err(e) = self.stop_worker(&ctx, &worker).await {
+                    tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+                }
+                let reason = exit_reason.as_deref().unwrap_or("health_check_failed");
+                let mut event = PoolEvent::new(EventKind::WorkerCrashed)
+                    .worker(&worker.id)
+                    .reason(reason);
+                if let Some(session) = pool
+                    .session_list
+                    .iter()
+                    .find(|s| s.worker_id == worker.id && s.available)
+                {
+                    event = event.session(&session.id).user(&session.user);
+                }
+                publish(&ctx, event).await?;
+                self.schedule_worker_history_prune(&ctx, &settings, &worker.id);
+                pool.worker_gone(&worker.id);
+                fail_sessions(&ctx, &mut pool.session_list, &worker.id, reason).await?;
+                count(&ctx, || {
+                    METRICS
+                        .reaper_deletions
+                        .with_label_values(&["unhealthy"])
+                        .inc()
                 })
                 .await?;
-            if health_status == "ok".to_string() {
-                worker.available = true;
-                healthy_workers.push(worker);
             }
         }
 
         pool.worker_list = healthy_workers;
 
         ctx.set("pool_state", serde_json::to_vec(&pool)?);
+        pool.observe();
+        METRICS.warm_pool_idle.set(pool.idle_workers() as i64);
+        if pool.warm_workers() < settings.warm_pool.size {
+            self.schedule_replenish(&ctx);
+        }
         Ok(())
     }
 
+    // Brings the persisted pool in line with what is actually running: recorded workers that
+    // no longer answer are dropped and their sessions failed, running workers the pool has no
+    // record of are adopted or killed
+    #[tracing::instrument(
+        skip_all,
+        fields(request_id = telemetry::restate_request_id(ctx.headers()))
+    )]
+    async fn reconcile(
+        &self,
+        ctx: ObjectContext<'_>,
+    ) -> Result<RestateJson<ReconcileReport>, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => Pool::default(),
+        };
+        let mut report = ReconcileReport::default();
+        let settings = self.settings(&ctx).await?;
+
+        let mut live_workers = Vec::new();
+        for worker in std::mem::take(&mut pool.worker_list) {
+            let span = side_effect_span("health", None, &worker);
+            let backend = self.backend.clone();
+            let worker_ref = worker.backend_ref();
+            let alive: bool = ctx
+                .run(move || async move { Ok(backend.probe(&worker_ref).await) })
+                .instrument(span)
+                .await?;
+            if alive {
+                live_workers.push(worker);
+                continue;
+            }
+            tracing::warn!(worker_id = %worker.id, "recorded worker is gone");
+            report.dead_workers += 1;
+            let reason = self.exit_reason(&ctx, &worker).await?;
+            if let Err(e) = self.stop_worker(&ctx, &worker).await {
+                tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+            }
+            publish(
+                &ctx,
+                PoolEvent::new(EventKind::WorkerCrashed)
+                    .worker(&worker.id)
+                    .reason(reason.as_deref().unwrap_or("reconcile")),
+            )
+            .await?;
+            self.schedule_worker_history_prune(&ctx, &settings, &worker.id);
+            pool.worker_gone(&worker.id);
+            let reason = reason.as_deref().unwrap_or("worker_lost");
+            let failed = fail_sessions(&ctx, &mut pool.session_list, &worker.id, reason).await?;
+            report.failed_sessions += failed;
+            count(&ctx, || {
+                METRICS
+                    .reaper_deletions
+                    .with_label_values(&["worker_lost"])
+                    .inc_by(failed as u64)
+            })
+            .await?;
+        }
+        pool.worker_list = live_workers;
+
+        let backend = self.backend.clone();
+        let RestateJson(discovered) = ctx
+            .run(|| async move {
+                backend.discover().await.map(RestateJson).map_err(|e| {
+                    TerminalError::new(format!("Failed to discover workers: {e:#}")).into()
+                })
+            })
+            .instrument(tracing::info_span!("side_effect", op = "discover_workers"))
+            .await?;
+        let policy = settings.reconcile.unknown_workers;
+
+        for found in discovered {
+            if pool.worker_list.iter().any(|w| w.id == found.id) {
+                continue;
+            }
+            let mut worker = Worker {
+                id: found.id.clone(),
+                port: found.port,
+                available: true,
+                token: found.token.clone(),
+                handle: found.handle.clone(),
+                over_budget_since: None,
+                ..Default::default()
+            };
+            if policy == UnknownWorkers::Adopt
+                && let Some(session) = self.adopt(&ctx, &worker).await?
+            {
+                tracing::info!(worker_id = %worker.id, "adopted running worker");
+                report.adopted += 1;
+                let mut event = PoolEvent::new(EventKind::WorkerAdopted)
+                    .worker(&worker.id)
+                    .reason("reconcile");
+                // It may not have been started with today's settings, it keeps what it holds
+                worker.capacity = settings.worker.session_capacity;
+                worker.labels = settings.scheduler.worker_labels.clone();
+                worker.resources = settings.scheduler.worker_resources.clone();
+                if let Some(session) = &session {
+                    event = event.session(&session.id).user(&session.user);
+                    pool.session_list.insert(0, session.clone());
+                }
+                pool.worker_list.insert(0, worker);
+                publish(&ctx, event).await?;
+                continue;
+            }
+            tracing::warn!(worker_id = %worker.id, "killing unknown worker");
+            report.killed += 1;
+            if let Err(e) = self.stop_worker(&ctx, &worker).await {
+                tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+            }
+            count(&ctx, || {
+                METRICS
+                    .reaper_deletions
+                    .with_label_values(&["orphaned"])
+                    .inc()
+            })
+            .await?;
+        }
+
+        ctx.set("pool_state", serde_json::to_vec(&pool)?);
+        pool.observe();
+        if pool.warm_workers() < settings.warm_pool.size {
+            self.schedule_replenish(&ctx);
+        }
+        tracing::info!(?report, "reconciled pool state");
+        Ok(RestateJson(report))
+    }
+
+    // Starts one warm worker when the warm pool is short and sends itself again while it still
+    // is. One worker per invocation keeps the pool free for session calls in between.
+    #[tracing::instrument(
+        skip_all,
+        fields(request_id = telemetry::restate_request_id(ctx.headers()))
+    )]
+    async fn replenish_warm_pool(&self, mut ctx: ObjectContext<'_>) -> Result<(), HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let config = self.settings(&ctx).await?;
+        if draining(&ctx).await? {
+            return Ok(());
+        }
+        let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => Pool::default(),
+        };
+        let warm = pool.warm_workers();
+        if warm >= config.warm_pool.size {
+            return Ok(());
+        }
+
+        // A spawn failure isn't retried here, the next poll or session tries again
+        let mut worker = match self.start_worker(&mut ctx, &config, "", None).await {
+            Ok(worker) => worker,
+            Err(e) => {
+                tracing::warn!(error = ?e, "failed to start warm worker");
+                return Ok(());
+            }
+        };
+        // Not available until its probe answers
+        worker.available = false;
+        worker.generation = pool.generation;
+        announce_replacement(&ctx, &mut pool, &worker, None).await?;
+        let worker_id = worker.id.clone();
+        pool.worker_list.push(worker);
+        ctx.set("pool_state", serde_json::to_vec(&pool)?);
+        pool.observe();
+
+        self.schedule_warm_check(
+            &ctx,
+            WarmCheck {
+                worker_id,
+                attempt: 0,
+            },
+            config.worker.startup_delay(),
+        );
+        if warm + 1 < config.warm_pool.size {
+            self.schedule_replenish(&ctx);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            worker_id = %check.worker_id,
+        )
+    )]
+    async fn check_warm_worker(
+        &self,
+        ctx: ObjectContext<'_>,
+        RestateJson(check): RestateJson<WarmCheck>,
+    ) -> Result<(), HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => return Ok(()),
+        };
+        // Gone, or already handed out or ready
+        let Some(index) = pool
+            .worker_list
+            .iter()
+            .position(|w| w.id == check.worker_id && !w.available && !pool.has_session(w))
+        else {
+            return Ok(());
+        };
+
+        let span = side_effect_span("health", None, &pool.worker_list[index]);
+        let backend = self.backend.clone();
+        let worker_ref = pool.worker_list[index].backend_ref();
+        let ready: bool = ctx
+            .run(move || async move { Ok(backend.probe(&worker_ref).await) })
+            .instrument(span)
+            .await?;
+        if ready {
+            pool.worker_list[index].available = true;
+        } else {
+            let settings = self.settings(&ctx).await?;
+            let attempts = settings.warm_pool.ready_timeout_secs * 1000
+                / WARM_PROBE_INTERVAL.as_millis() as u64;
+            if check.attempt < attempts {
+                self.schedule_warm_check(
+                    &ctx,
+                    WarmCheck {
+                        attempt: check.attempt + 1,
+                        ..check
+                    },
+                    WARM_PROBE_INTERVAL,
+                );
+                return Ok(());
+            }
+            tracing::warn!(worker_id = %check.worker_id, "warm worker never became ready");
+            let worker = pool.worker_list.remove(index);
+            if let Err(e) = self.stop_worker(&ctx, &worker).await {
+                tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+            }
+            WORKER_LOGS.remove(&worker.id);
+            count(&ctx, || METRICS.spawn_failed("warm_not_ready")).await?;
+        }
+
+        ctx.set("pool_state", serde_json::to_vec(&pool)?);
+        pool.observe();
+        METRICS.warm_pool_idle.set(pool.idle_workers() as i64);
+        Ok(())
+    }
+
+    // Stops every worker and ends their sessions, for a shutdown that doesn't leave workers to
+    // a successor
+    #[tracing::instrument(
+        skip_all,
+        fields(request_id = telemetry::restate_request_id(ctx.headers()))
+    )]
+    async fn terminate_workers(&self, ctx: ObjectContext<'_>) -> Result<u64, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => return Ok(0),
+        };
+        let settings = self.settings(&ctx).await?;
+        let stopped = pool.worker_list.len() as u64;
+        for worker in &pool.worker_list {
+            if let Err(e) = self.stop_worker(&ctx, worker).await {
+                tracing::warn!(worker_id = %worker.id, error = %e, "failed to stop worker");
+            }
+            WORKER_LOGS.remove(&worker.id);
+            self.schedule_worker_history_prune(&ctx, &settings, &worker.id);
+        }
+        for session in &pool.session_list {
+            publish(
+                &ctx,
+                PoolEvent::new(EventKind::SessionDeleted)
+                    .session(&session.id)
+                    .worker(&session.worker_id)
+                    .user(&session.user)
+                    .reason("shutdown"),
+            )
+            .await?;
+            self.schedule_history_prune(&ctx, &settings, &session.id);
+        }
+        ctx.set("pool_state", serde_json::to_vec(&Pool::default())?);
+        METRICS.observe_pool(0, 0);
+        Ok(stopped)
+    }
+
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
     async fn health_check(
         &self,
         ctx: ObjectContext<'_>,
         session_id: String,
     ) -> Result<String, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
         let pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => Pool::default(),
@@ -195,27 +1562,28 @@ impl WorkerPoolService for Pool {
             .session_list
             .iter()
             .find(|s| s.id == session_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session from session_list"
-            )))?;
+            .ok_or(TerminalError::new(
+                "Error fetching session from session_list",
+            ))?;
+        check_owner(ctx.headers(), Some(&session.user))?;
+        session.check_alive()?;
 
         let worker = pool
             .worker_list
             .iter()
             .find(|w| w.id == session.worker_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session_worker from worker_list"
-            )))?;
+            .ok_or(TerminalError::new(
+                "Error fetching session_worker from worker_list",
+            ))?;
 
-        let worker_port = worker
-            .port
-            .ok_or(TerminalError::new(format!("Error fetching worker port")))?;
+        let base = self.endpoint(&ctx, worker).await?;
+        let span = side_effect_span("health", Some(&session.id), worker);
 
-        let client = Client::new();
+        let client = worker_client(&worker.token, &span)?;
         let health_status: String = ctx
             .run(move || async move {
                 let response = client
-                    .get(format!("http://localhost:{}/health", worker_port))
+                    .get(format!("{}/health", base))
                     .send()
                     .await
                     .map_err(|e| {
@@ -228,15 +1596,35 @@ impl WorkerPoolService for Pool {
 
                 Ok(body)
             })
+            .instrument(span)
             .await?;
 
+        // Only traced in the session's history, it does not extend the TTL, which runs from
+        // creation. Too frequent to broadcast.
+        let mut keepalive = PoolEvent::new(EventKind::SessionKeepalive)
+            .session(&session.id)
+            .worker(&worker.id)
+            .user(&session.user)
+            .caller(ctx.headers());
+        keepalive.timestamp = ctx.run(|| async { Ok(unix_now()) }).await?;
+        record_history(&ctx, keepalive).await?;
+
         Ok(health_status)
     }
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
     async fn status_check(
         &self,
         ctx: ObjectContext<'_>,
         session_id: String,
     ) -> Result<RestateJson<SessionStatusResponse>, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
         let pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => Pool::default(),
@@ -246,27 +1634,28 @@ impl WorkerPoolService for Pool {
             .session_list
             .iter()
             .find(|s| s.id == session_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session from session_list"
-            )))?;
+            .ok_or(TerminalError::new(
+                "Error fetching session from session_list",
+            ))?;
+        check_owner(ctx.headers(), Some(&session.user))?;
+        session.check_alive()?;
 
         let worker = pool
             .worker_list
             .iter()
             .find(|w| w.id == session.worker_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session_worker from worker_list"
-            )))?;
+            .ok_or(TerminalError::new(
+                "Error fetching session_worker from worker_list",
+            ))?;
 
-        let worker_port = worker
-            .port
-            .ok_or(TerminalError::new(format!("Error fetching worker port")))?;
+        let base = self.endpoint(&ctx, worker).await?;
+        let span = side_effect_span("status", Some(&session.id), worker);
 
-        let client = Client::new();
+        let client = worker_client(&worker.token, &span)?;
         let status_response: String = ctx
             .run(move || async move {
                 let response = client
-                    .get(format!("http://localhost:{}/status", worker_port))
+                    .get(format!("{}/status", base))
                     .send()
                     .await
                     .map_err(|e| {
@@ -279,55 +1668,128 @@ impl WorkerPoolService for Pool {
 
                 Ok(body)
             })
+            .instrument(span)
             .await?;
-        let parsed: SessionStatusResponse = serde_json::from_str(&status_response.clone())
+        let mut parsed: SessionStatusResponse = serde_json::from_str(&status_response.clone())
             .map_err(|e| TerminalError::new(format!("Invalid JSON response: {}", e)))?;
+        // Workers report their first session, the status covers the worker as a whole
+        parsed.session_id = session.id.clone();
+        parsed.usage = self.usage(&ctx, worker).await?;
 
         Ok(RestateJson(parsed))
     }
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            user = %request.user,
+        )
+    )]
     async fn spawn_worker(
         &self,
         mut ctx: ObjectContext<'_>,
-        user: String,
+        RestateJson(request): RestateJson<CreateSessionRequest>,
     ) -> Result<RestateJson<CreateSessionResponse>, HandlerError> {
-        let ready_port = get_port();
-        ctx.run(|| async {
-            Command::new("steel-browser")
-                .env("PORT", ready_port.unwrap_or_default().to_string())
-                .spawn()
-                .map(|_| ())
-                .map_err(|e| {
-                    TerminalError::new(format!("Error starting steel-browser: {}", e)).into()
-                })
-        })
-        .await?;
-
-        // Give it a moment to start
-        ctx.sleep(std::time::Duration::from_millis(500)).await?;
-        let worker_id = ctx.rand_uuid().to_string();
-
-        let worker = Worker {
-            id: worker_id.clone(),
-            port: ready_port,
-            available: true,
-        };
-
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        if draining(&ctx).await? {
+            return Err(
+                TerminalError::new_with_code(503, "Draining, not placing new sessions").into(),
+            );
+        }
+        let started = ctx.run(|| async { Ok(unix_millis()) }).await?;
+        let config = self.settings(&ctx).await?;
         let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => Pool::default(),
         };
+
+        let CreateSessionRequest { user, placement } = request;
+        placement
+            .resources
+            .validate()
+            .map_err(|e| TerminalError::new_with_code(400, e))?;
+        // Assigned up front so the session's history starts with placement, workers are asked
+        // to take it the way steel-browser takes `sessionId`
+        let session_id = ctx.rand_uuid().to_string();
+        let created = publish(
+            &ctx,
+            PoolEvent::new(EventKind::SessionCreated)
+                .session(&session_id)
+                .user(&user)
+                .caller(ctx.headers()),
+        )
+        .await?;
+
+        // A worker that fits takes the session as is, otherwise the session pays the cold start
+        let fresh = Candidate {
+            worker_id: "",
+            node: None,
+            labels: &config.scheduler.worker_labels,
+            accepting: true,
+            sessions: 0,
+            capacity: config.worker.session_capacity,
+            offered: &config.scheduler.worker_resources,
+            requested: Resources::default(),
+        };
+        let decision = scheduler::place(
+            config.scheduler.strategy,
+            &pool.candidates(&config.recycle),
+            &placement,
+            &fresh,
+        );
+        let (mut worker, cold) = match decision.placement {
+            Placement::Worker(index) => {
+                if !pool.has_session(&pool.worker_list[index]) {
+                    count(&ctx, || METRICS.warm_pool_hits.inc()).await?;
+                }
+                (pool.worker_list.remove(index), false)
+            }
+            Placement::Start => {
+                if config.warm_pool.size > 0 {
+                    count(&ctx, || METRICS.warm_pool_misses.inc()).await?;
+                }
+                let mut worker = self
+                    .start_worker(&mut ctx, &config, &user, Some(&created))
+                    .await?;
+                worker.generation = pool.generation;
+                announce_replacement(&ctx, &mut pool, &worker, Some(&created)).await?;
+                (worker, true)
+            }
+            Placement::Unschedulable => {
+                abandon_spawn(&ctx, "unschedulable", Some(&created)).await?;
+                return Err(TerminalError::new_with_code(422, decision.reason).into());
+            }
+        };
+        publish(
+            &ctx,
+            PoolEvent::new(EventKind::SessionPlaced)
+                .session(&session_id)
+                .worker(&worker.id)
+                .user(&user)
+                .reason(&decision.reason),
+        )
+        .await?;
+        worker.sessions_served += 1;
+        let worker_id = worker.id.clone();
+
+        if cold {
+            // Give it a moment to start
+            ctx.sleep(config.worker.startup_delay()).await?;
+        }
+
         // Update worker
         pool.worker_list.insert(0, worker.clone());
-        let worker_port = worker
-            .port
-            .ok_or(TerminalError::new(format!("Error fetching worker port")))?;
-        let spawn_session: String = ctx
+        let base = self.endpoint(&ctx, &worker).await;
+        let base = spawn_failed(&ctx, "no_endpoint", Some(&created), base).await?;
+        let span = side_effect_span("create_session", Some(&session_id), &worker);
+        let body = serde_json::json!({ "sessionId": session_id, "user": user });
+        let client = worker_client(&worker.token, &span)?;
+        let spawn_session = ctx
             .run(move || async move {
-                let client = Client::new();
-
                 let response = client
-                    .post(format!("http://localhost:{}/sessions", worker_port))
-                    .json(&serde_json::json!({ "user": user }))
+                    .post(format!("{}/sessions", base))
+                    .json(&body)
                     .send()
                     .await
                     .map_err(|e| TerminalError::new(format!("Failed to create session: {}", e)))?;
@@ -338,30 +1800,82 @@ impl WorkerPoolService for Pool {
 
                 Ok(body)
             })
-            .await?;
-        let parsed: CreateSessionResponse = serde_json::from_str(&spawn_session.clone())
-            .map_err(|e| TerminalError::new(format!("Invalid JSON response: {}", e)))?;
+            .instrument(span)
+            .await;
+        let spawn_session: String =
+            spawn_failed(&ctx, "session_create", Some(&created), spawn_session).await?;
+        let parsed = serde_json::from_str::<CreateSessionResponse>(&spawn_session)
+            .map_err(|e| TerminalError::new(format!("Invalid JSON response: {}", e)))
+            .and_then(|parsed| {
+                if parsed.id == session_id {
+                    Ok(parsed)
+                } else {
+                    Err(TerminalError::new(format!(
+                        "Worker created session {} instead of {}",
+                        parsed.id, session_id
+                    )))
+                }
+            });
+        let parsed = spawn_failed(&ctx, "invalid_response", Some(&created), parsed).await?;
+        let created_at = ctx.run(|| async { Ok(unix_now()) }).await?;
 
         let session = Session {
             id: parsed.id.clone(),
             available: true,
             worker_id: worker_id.clone(),
             user: parsed.data.user.clone(),
-            // last_active: parsed.created_at.clone(),
+            failure: None,
+            resources: placement.resources,
+            created_at,
         };
         // Update Session
         pool.session_list.insert(0, session.clone());
         let bytes = serde_json::to_vec(&pool)?;
         // Persist state
         ctx.set("pool_state", bytes);
+        publish(
+            &ctx,
+            PoolEvent::new(EventKind::SessionReady)
+                .session(&session.id)
+                .worker(&worker_id)
+                .user(&session.user),
+        )
+        .await?;
+        pool.observe();
+        METRICS.warm_pool_idle.set(pool.idle_workers() as i64);
+        if config.warm_pool.size > 0 {
+            self.schedule_replenish(&ctx);
+        }
+        // Measured from the journaled start, a replay doesn't restart the clock
+        count(&ctx, || {
+            METRICS
+                .spawn_latency
+                .observe((unix_millis() - started) as f64 / 1000.0)
+        })
+        .await?;
+        tracing::info!(
+            session_id = %parsed.id,
+            worker_id = %worker_id,
+            port = worker.port,
+            "session ready"
+        );
         Ok(RestateJson(parsed))
     }
 
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
     async fn get_session(
         &self,
         ctx: ObjectContext<'_>,
         session_id: String,
     ) -> Result<RestateJson<CreateSessionResponse>, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
         let pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => Pool::default(),
@@ -371,30 +1885,28 @@ impl WorkerPoolService for Pool {
             .session_list
             .iter()
             .find(|s| s.id == session_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session from session_list"
-            )))?;
+            .ok_or(TerminalError::new(
+                "Error fetching session from session_list",
+            ))?;
+        check_owner(ctx.headers(), Some(&session.user))?;
+        session.check_alive()?;
 
         let worker = pool
             .worker_list
             .iter()
             .find(|w| w.id == session.worker_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session_worker from worker_list"
-            )))?;
+            .ok_or(TerminalError::new(
+                "Error fetching session_worker from worker_list",
+            ))?;
 
-        let worker_port = worker
-            .port
-            .ok_or(TerminalError::new(format!("Error fetching worker port")))?;
+        let base = self.endpoint(&ctx, worker).await?;
+        let span = side_effect_span("get_session", Some(&session.id), worker);
 
-        let client = Client::new();
+        let client = worker_client(&worker.token, &span)?;
         let session_body: String = ctx
             .run(move || async move {
                 let response = client
-                    .get(format!(
-                        "http://localhost:{}/sessions/{}",
-                        worker_port, session.id
-                    ))
+                    .get(format!("{}/sessions/{}", base, session.id))
                     .send()
                     .await
                     .map_err(|e| {
@@ -406,16 +1918,23 @@ impl WorkerPoolService for Pool {
 
                 Ok(body)
             })
+            .instrument(span)
             .await?;
         let parsed: CreateSessionResponse = serde_json::from_str(&session_body.clone())
             .map_err(|e| TerminalError::new(format!("Invalid JSON response: {}", e)))?;
 
         Ok(RestateJson(parsed))
     }
+    #[tracing::instrument(
+        skip_all,
+        fields(request_id = telemetry::restate_request_id(ctx.headers()))
+    )]
     async fn get_all_sessions(
         &self,
         ctx: ObjectContext<'_>,
     ) -> Result<RestateJson<Vec<CreateSessionResponse>>, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
         let pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => Pool::default(),
@@ -423,49 +1942,127 @@ impl WorkerPoolService for Pool {
 
         let mut results: Vec<CreateSessionResponse> = Vec::new();
 
-        for worker in &pool.worker_list {
-            let Some(port) = worker.port else {
-                continue;
-            };
+        // Asked per session, a worker's status only reports one of its sessions
+        let owner = ctx.headers().get(auth::OWNER_HEADER).cloned();
+        for session in pool
+            .session_list
+            .iter()
+            .filter(|s| s.available && owner.as_ref().is_none_or(|o| *o == s.user))
+        {
+            // One worker that can't answer doesn't hide the other sessions
+            match self.fetch_session(&ctx, &pool, session).await {
+                Ok(parsed) => results.push(parsed),
+                Err(e) => results.push(CreateSessionResponse {
+                    id: session.id.clone(),
+                    created_at: session.created_at,
+                    data: Data {
+                        user: session.user.clone(),
+                    },
+                    error: Some(format!("Status unknown: {}", e.message())),
+                }),
+            }
+        }
+        Ok(RestateJson(results))
+    }
+
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
+    async fn delete_session(
+        &self,
+        ctx: ObjectContext<'_>,
+        session_id: String,
+    ) -> Result<String, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => Pool::default(),
+        };
+
+        let session = pool
+            .session_list
+            .iter()
+            .find(|s| s.id == session_id)
+            .cloned()
+            .ok_or(TerminalError::new(
+                "Error fetching session from session_list",
+            ))?;
+        check_owner(ctx.headers(), Some(&session.user))?;
 
-            let client = Client::new();
-            let body: String = ctx
-                .run(move || async move {
+        // A failed session's worker is gone, there is only the record left to drop
+        let worker = pool
+            .worker_list
+            .iter()
+            .find(|w| w.id == session.worker_id && session.available)
+            .cloned();
+        let delete_session = match worker {
+            Some(worker) => {
+                let base = self.endpoint(&ctx, &worker).await?;
+                let span = side_effect_span("delete_session", Some(&session.id), &worker);
+
+                let client = worker_client(&worker.token, &span)?;
+                let session_id = session.id.clone();
+                ctx.run(move || async move {
                     let response = client
-                        .get(format!("http://localhost:{}/status", port))
+                        .delete(format!("{}/sessions/{}", base, session_id))
                         .send()
                         .await
                         .map_err(|e| {
-                            TerminalError::new(format!(
-                                "Failed to send get_all_sessions request: {}",
-                                e
-                            ))
+                            TerminalError::new(format!("Failed to send delete request: {}", e))
                         })?;
 
                     let body = response.text().await.map_err(|e| {
-                        TerminalError::new(format!(
-                            "Failed to get_all_sessions health response body: {}",
-                            e
-                        ))
+                        TerminalError::new(format!("Failed to read delete response body: {}", e))
                     })?;
 
                     Ok(body)
                 })
-                .await?;
-            let parsed: CreateSessionResponse = serde_json::from_str(&body)
-                .map_err(|e| TerminalError::new(format!("Invalid session JSON: {}", e)))?;
+                .instrument(span)
+                .await?
+            }
+            None => String::new(),
+        };
 
-            results.push(parsed);
-        }
-        Ok(RestateJson(results))
+        let event = PoolEvent::new(EventKind::SessionDeleted)
+            .session(&session_id)
+            .worker(&session.worker_id)
+            .user(&session.user)
+            .reason("deleted")
+            .caller(ctx.headers());
+        pool.session_list.retain(|s| s.id != session_id);
+        let settings = self.settings(&ctx).await?;
+        self.release_slot(&ctx, &settings, &mut pool, &session.worker_id)
+            .await?;
+        let bytes = serde_json::to_vec(&pool)?;
+        // Persist state
+        ctx.set("pool_state", bytes);
+        publish(&ctx, event).await?;
+        self.schedule_history_prune(&ctx, &settings, &session_id);
+        pool.observe();
+        Ok(delete_session)
     }
 
-    async fn delete_session(
+    // Maps a session to the worker id its logs are captured under
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
+    async fn resolve_worker(
         &self,
         ctx: ObjectContext<'_>,
         session_id: String,
     ) -> Result<String, HandlerError> {
-        let mut pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let pool: Pool = match ctx.get::<Vec<u8>>("pool_state").await? {
             Some(bytes) => serde_json::from_slice(&bytes)?,
             None => Pool::default(),
         };
@@ -474,48 +2071,79 @@ impl WorkerPoolService for Pool {
             .session_list
             .iter()
             .find(|s| s.id == session_id)
-            .ok_or(TerminalError::new(format!(
-                "Error fetching session from session_list"
-            )))?;
-
-        let idx = pool
-            .worker_list
-            .iter()
-            .position(|w| w.id == session.worker_id)
-            .ok_or(TerminalError::new(
-                "Error fetching session_worker from worker_list",
+            .ok_or(TerminalError::new_with_code(
+                404,
+                "Error fetching session from session_list",
             ))?;
-        let worker = pool.worker_list.remove(idx);
-        let worker_port = worker
-            .port
-            .ok_or(TerminalError::new(format!("Error fetching worker port")))?;
+        check_owner(ctx.headers(), Some(&session.user))?;
 
-        let client = Client::new();
-        let delete_session: String = ctx
-            .run(move || async move {
-                let response = client
-                    .delete(format!(
-                        "http://localhost:{}/sessions/{}",
-                        worker_port, session.id
-                    ))
-                    .send()
-                    .await
-                    .map_err(|e| {
-                        TerminalError::new(format!("Failed to send delete request: {}", e))
-                    })?;
+        Ok(session.worker_id.clone())
+    }
 
-                let body = response.text().await.map_err(|e| {
-                    TerminalError::new(format!("Failed to read delete response body: {}", e))
-                })?;
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
+    async fn get_history(
+        &self,
+        ctx: ObjectContext<'_>,
+        session_id: String,
+    ) -> Result<RestateJson<Vec<PoolEvent>>, HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        telemetry::continue_restate_trace(ctx.headers());
+        let Some(mut history) = read_history(&ctx, &history_key(&session_id)).await? else {
+            return Err(TerminalError::new_with_code(404, "No history for this session").into());
+        };
+        // Outlives the session, its events say whose it was
+        let user = history.iter().find_map(|e| e.user.as_deref());
+        check_owner(ctx.headers(), user)?;
+        // What happened to the worker outside any session, such as its retirement
+        if let Some(worker_id) = history.iter().find_map(|e| e.worker_id.clone())
+            && let Some(worker_history) =
+                read_history(&ctx, &worker_history_key(&worker_id)).await?
+        {
+            history.extend(worker_history);
+            history.sort_by_key(|e| e.timestamp);
+        }
+        Ok(RestateJson(history))
+    }
 
-                Ok(body)
-            })
-            .await?;
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            session_id = %session_id,
+        )
+    )]
+    async fn prune_history(
+        &self,
+        ctx: ObjectContext<'_>,
+        session_id: String,
+    ) -> Result<(), HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        ctx.clear(&history_key(&session_id));
+        Ok(())
+    }
 
-        let bytes = serde_json::to_vec(&pool)?;
-        // Persist state
-        ctx.set("pool_state", bytes);
-        Ok(delete_session)
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            request_id = telemetry::restate_request_id(ctx.headers()),
+            worker_id = %worker_id,
+        )
+    )]
+    async fn prune_worker_history(
+        &self,
+        ctx: ObjectContext<'_>,
+        worker_id: String,
+    ) -> Result<(), HandlerError> {
+        self.verify_caller(ctx.headers())?;
+        ctx.clear(&worker_history_key(&worker_id));
+        WORKER_LOGS.remove(&worker_id);
+        Ok(())
     }
 }
 #[utoipa::path(
@@ -526,14 +2154,20 @@ impl WorkerPoolService for Pool {
     ),
     responses(
         (status = 200, description = "health check status", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
         (status = 500, description = "Internal server error", body = String)
     )
 )]
+#[tracing::instrument(skip_all, fields(session_id = %id, invocation_id = tracing::field::Empty))]
 async fn health(
     State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
     Path(id): Path<String>,
 ) -> Result<String, (StatusCode, String)> {
-    let client = Client::new();
+    principal.require(Scope::SessionsRead)?;
+    let client = state.ingress_client(&request_id, &principal)?;
 
     let url = format!(
         "{}/WorkerPoolService/pool/health_check",
@@ -546,6 +2180,7 @@ async fn health(
             format!("Failed to send health request: {e}"),
         )
     })?;
+    telemetry::record_invocation(&response);
 
     let body = response.text().await.map_err(|e| {
         (
@@ -560,18 +2195,24 @@ async fn health(
     get,
     path = "/status/{id}",
     params(
-        ("id" = String, Path, description = "session id")  // ← Change to Path
+        ("id" = String, Path, description = "session id")
     ),
     responses(
         (status = 200, description = "Status check of worker", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
         (status = 500, description = "Internal server error", body = String)
     )
 )]
+#[tracing::instrument(skip_all, fields(session_id = %id, invocation_id = tracing::field::Empty))]
 async fn status(
     State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
     Path(id): Path<String>,
 ) -> Result<String, (StatusCode, String)> {
-    let client = Client::new();
+    principal.require(Scope::SessionsRead)?;
+    let client = state.ingress_client(&request_id, &principal)?;
 
     let url = format!(
         "{}/WorkerPoolService/pool/status_check",
@@ -584,6 +2225,7 @@ async fn status(
             format!("Failed to send status request: {e}"),
         )
     })?;
+    telemetry::record_invocation(&response);
 
     response.text().await.map_err(|e| {
         (
@@ -600,15 +2242,21 @@ async fn status(
     ),
     responses(
         (status = 200, description = "session details", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
         (status = 500, description = "Internal server error", body = String),
         (status = 404, description = "Not Found", body = String)
     )
 )]
+#[tracing::instrument(skip_all, fields(session_id = %id, invocation_id = tracing::field::Empty))]
 pub async fn get_session(
     State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
     Path(id): Path<String>,
 ) -> Result<String, (StatusCode, String)> {
-    let client = Client::new();
+    principal.require(Scope::SessionsRead)?;
+    let client = state.ingress_client(&request_id, &principal)?;
 
     let url = format!(
         "{}/WorkerPoolService/pool/get_session",
@@ -621,27 +2269,252 @@ pub async fn get_session(
             format!("Failed to send get_session request: {e}"),
         )
     })?;
-
-    response.text().await.map_err(|e| {
+    telemetry::record_invocation(&response);
+    let status = response.status();
+    let raw = response.text().await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to read get_session response: {e}"),
         )
+    })?;
+    // Deleted and unknown sessions keep their status
+    if !status.is_success() {
+        return Err((
+            StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            raw,
+        ));
+    }
+    Ok(raw)
+}
+#[derive(Deserialize, IntoParams)]
+pub struct LogsQuery {
+    // Number of most recent lines to return, defaults to 100
+    tail: Option<usize>,
+    // Keep the connection open and stream new lines over SSE
+    follow: Option<bool>,
+}
+#[utoipa::path(
+    get,
+    path = "/session/{id}/logs",
+    params(
+        ("id" = String, Path, description = "session id"),
+        LogsQuery
+    ),
+    responses(
+        (status = 200, description = "worker stdout/stderr, SSE when following", body = Vec<LogLine>),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
+        (status = 404, description = "Not Found", body = String),
+        (status = 500, description = "Internal server error", body = String)
+    )
+)]
+#[tracing::instrument(skip_all, fields(session_id = %id, invocation_id = tracing::field::Empty))]
+pub async fn session_logs(
+    State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
+    Path(id): Path<String>,
+    Query(query): Query<LogsQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    principal.require(Scope::SessionsRead)?;
+    let client = state.ingress_client(&request_id, &principal)?;
+
+    let url = format!(
+        "{}/WorkerPoolService/pool/resolve_worker",
+        state.restate_base_url
+    );
+
+    let response = client.post(url).json(&id).send().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to send resolve_worker request: {e}"),
+        )
+    })?;
+    telemetry::record_invocation(&response);
+    let status = response.status();
+    let raw = response.text().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read resolve_worker response: {e}"),
+        )
+    })?;
+    if !status.is_success() {
+        return Err((
+            StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            raw,
+        ));
+    }
+    let worker_id: String = serde_json::from_str(&raw).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Invalid resolve_worker JSON: {e}"),
+        )
+    })?;
+
+    // Logs live in the process that spawned the worker
+    let log = WORKER_LOGS.get(&worker_id).ok_or((
+        StatusCode::NOT_FOUND,
+        "No logs captured for this session's worker".to_string(),
+    ))?;
+    let tail = query.tail.unwrap_or(100);
+
+    if !query.follow.unwrap_or(false) {
+        return Ok(Json(log.tail(tail)).into_response());
+    }
+
+    let (backlog, rx) = log.follow(tail);
+    let live = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(line) => return Some((line, rx)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = stream::iter(backlog)
+        .chain(live)
+        .map(|line| Event::default().json_data(line));
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+#[utoipa::path(
+    get,
+    path = "/session/{id}/history",
+    params(
+        ("id" = String, Path, description = "session id")
+    ),
+    responses(
+        (status = 200, description = "session lifecycle, oldest first", body = Vec<PoolEvent>),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
+        (status = 404, description = "Not Found", body = String),
+        (status = 500, description = "Internal server error", body = String)
+    )
+)]
+#[tracing::instrument(skip_all, fields(session_id = %id, invocation_id = tracing::field::Empty))]
+pub async fn session_history(
+    State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    principal.require(Scope::SessionsRead)?;
+    let client = state.ingress_client(&request_id, &principal)?;
+
+    let url = format!(
+        "{}/WorkerPoolService/pool/get_history",
+        state.restate_base_url
+    );
+
+    let response = client.post(url).json(&id).send().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to send get_history request: {e}"),
+        )
+    })?;
+    telemetry::record_invocation(&response);
+    let status = response.status();
+    let raw = response.text().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read get_history response: {e}"),
+        )
+    })?;
+    if !status.is_success() {
+        return Err((
+            StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            raw,
+        ));
+    }
+    Ok(([(CONTENT_TYPE, "application/json")], raw).into_response())
+}
+#[derive(Deserialize, IntoParams)]
+pub struct EventsQuery {
+    user: Option<String>,
+    session_id: Option<String>,
+    // Comma separated event kinds, e.g. session_ready,session_deleted
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    kind: Option<String>,
+}
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "SSE stream of pool lifecycle events", body = PoolEvent),
+        (status = 400, description = "Unknown event type", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String)
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn events(
+    principal: Principal,
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, String)> {
+    principal.require(Scope::SessionsRead)?;
+    // Callers restricted to their own sessions only hear about those
+    let user = match (principal.owner(), query.user) {
+        (Some(owner), Some(user)) if owner != user => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Only events of your own sessions can be streamed".to_string(),
+            ));
+        }
+        (Some(owner), _) => Some(owner.to_string()),
+        (None, user) => user,
+    };
+    let kinds = match &query.kind {
+        Some(raw) => raw
+            .split(',')
+            .map(|k| {
+                EventKind::parse(k.trim())
+                    .ok_or((StatusCode::BAD_REQUEST, format!("Unknown event type: {k}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let filter = EventFilter {
+        user,
+        session_id: query.session_id,
+        kinds,
+    };
+
+    let stream = stream::unfold(EVENTS.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
     })
+    .filter(move |event| std::future::ready(filter.matches(event)))
+    .map(|event| Event::default().event(event.kind.as_str()).json_data(event));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 #[utoipa::path(
     get,
     path = "/get_all_sessions",
     responses(
         (status = 200, description = "session details", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
         (status = 500, description = "Internal server error", body = String),
         (status = 404, description = "Not Found", body = String)
     )
 )]
+#[tracing::instrument(skip_all, fields(invocation_id = tracing::field::Empty))]
 pub async fn get_all_sessions(
     State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
 ) -> Result<String, (StatusCode, String)> {
-    let client = Client::new();
+    principal.require(Scope::SessionsRead)?;
+    let client = state.ingress_client(&request_id, &principal)?;
 
     let url = format!(
         "{}/WorkerPoolService/pool/get_all_sessions",
@@ -654,6 +2527,7 @@ pub async fn get_all_sessions(
             format!("Failed to send get_session request: {e}"),
         )
     })?;
+    telemetry::record_invocation(&response);
 
     response.text().await.map_err(|e| {
         (
@@ -665,39 +2539,63 @@ pub async fn get_all_sessions(
 #[utoipa::path(
     post,
     path = "/session",
+    request_body = CreateSessionRequest,
     responses(
         (status = 200, description = "session created", body = String),
-        (status = 500, description = "Internal server error", body = String)
+        (status = 400, description = "Requested resources out of range", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
+        (status = 422, description = "No worker can satisfy the placement constraints", body = String),
+        (status = 500, description = "Internal server error", body = String),
+        (status = 503, description = "Draining, not placing new sessions", body = String)
     )
 )]
+#[tracing::instrument(skip_all, fields(invocation_id = tracing::field::Empty))]
 pub async fn post_session(
     State(state): State<AppState>,
-    Json(payload): Json<Data>,
+    principal: Principal,
+    request_id: RequestId,
+    Json(mut payload): Json<CreateSessionRequest>,
 ) -> Result<Json<CreateSessionResponse>, (StatusCode, String)> {
-    let client = Client::new();
+    principal.require(Scope::SessionsCreate)?;
+    if state.lifecycle.is_draining() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Draining, not placing new sessions".to_string(),
+        ));
+    }
+    let client = state.ingress_client(&request_id, &principal)?;
+    // Authenticated JWT subjects own their sessions, whatever the body says
+    if let Some(subject) = principal.subject {
+        payload.user = subject;
+    }
 
     let url = format!(
         "{}/WorkerPoolService/pool/spawn_worker",
         state.restate_base_url
     );
 
-    let response = client
-        .post(url)
-        .json(&payload.user)
-        .send()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to spawn session: {e}"),
-            )
-        })?;
+    let response = client.post(url).json(&payload).send().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to spawn session: {e}"),
+        )
+    })?;
+    telemetry::record_invocation(&response);
+    let status = response.status();
     let raw = response.text().await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to read spawn response: {e}"),
         )
     })?;
+    // Unschedulable sessions and a draining pool keep their status
+    if !status.is_success() {
+        return Err((
+            StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            raw,
+        ));
+    }
 
     let session: CreateSessionResponse = serde_json::from_str(&raw).map_err(|e| {
         (
@@ -717,14 +2615,20 @@ pub async fn post_session(
     ),
     responses(
         (status = 200, description = "session deleted", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String),
         (status = 500, description = "Internal server error", body = String)
     )
 )]
+#[tracing::instrument(skip_all, fields(session_id = %id, invocation_id = tracing::field::Empty))]
 pub async fn delete_session(
     State(state): State<AppState>,
+    principal: Principal,
+    request_id: RequestId,
     Path(id): Path<String>,
 ) -> Result<String, (StatusCode, String)> {
-    let client = Client::new();
+    principal.require(Scope::SessionsDelete)?;
+    let client = state.ingress_client(&request_id, &principal)?;
 
     let url = format!(
         "{}/WorkerPoolService/pool/delete_session",
@@ -737,6 +2641,7 @@ pub async fn delete_session(
             format!("Failed to delete session: {e}"),
         )
     })?;
+    telemetry::record_invocation(&response);
 
     response.text().await.map_err(|e| {
         (
@@ -746,14 +2651,228 @@ pub async fn delete_session(
     })
 }
 
-// Affected by TOCTOU, fix for improvement
-pub fn get_port() -> Option<u16> {
-    let min_port: u16 = 3000;
-    let max_port: u16 = u16::MAX;
-    for p in min_port..max_port {
-        if TcpListener::bind(("0.0.0.0", p)).is_ok() {
-            return Some(p);
+#[utoipa::path(
+    get,
+    path = "/admin/config",
+    responses(
+        (status = 200, description = "effective config, secrets redacted", body = Object),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String)
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_config(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    principal.require(Scope::Admin)?;
+    serde_json::to_value(state.config.get().redacted())
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+#[utoipa::path(
+    put,
+    path = "/admin/config",
+    request_body(content = Object, description = "JSON merge patch over the running config"),
+    responses(
+        (status = 200, description = "reloaded, lists applied and restart only settings", body = Object),
+        (status = 400, description = "Invalid config, nothing was applied", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String)
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn put_config(
+    State(state): State<AppState>,
+    principal: Principal,
+    Json(patch): Json<serde_json::Value>,
+) -> Result<Json<ReloadOutcome>, (StatusCode, String)> {
+    principal.require(Scope::Admin)?;
+    state
+        .config
+        .get()
+        .patched(patch)
+        .and_then(|next| state.config.reload(next))
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!(error = %e, "config reload rejected");
+            (StatusCode::BAD_REQUEST, format!("Invalid config: {e:#}"))
+        })
+}
+#[derive(Serialize, ToSchema)]
+pub struct DrainResponse {
+    draining: bool,
+}
+#[utoipa::path(
+    post,
+    path = "/admin/drain",
+    responses(
+        (status = 200, description = "no new sessions are placed, existing ones are kept", body = DrainResponse),
+        (status = 401, description = "Missing or invalid credentials", body = String),
+        (status = 403, description = "Missing required scope", body = String)
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn drain(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<DrainResponse>, (StatusCode, String)> {
+    principal.require(Scope::Admin)?;
+    if state.lifecycle.drain() {
+        tracing::info!("draining, new sessions are refused");
+    }
+    Ok(Json(DrainResponse { draining: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_admins_may_drain() {
+        let state = AppState {
+            restate_base_url: String::new(),
+            ingress_secret: None,
+            config: ConfigHandle::default(),
+            lifecycle: Box::leak(Box::new(Lifecycle::new())),
+        };
+        let principal = |scopes| Principal {
+            subject: Some("alice".to_string()),
+            api_key_id: None,
+            scopes,
+        };
+        for scopes in [vec![], vec![Scope::SessionsCreate, Scope::SessionsDelete]] {
+            let (status, _) = drain(State(state.clone()), principal(scopes))
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(status, StatusCode::FORBIDDEN);
         }
+        assert!(!state.lifecycle.is_draining());
+
+        let Json(response) = drain(State(state.clone()), principal(vec![Scope::Admin]))
+            .await
+            .unwrap();
+        assert!(response.draining);
+        assert!(state.lifecycle.is_draining());
+    }
+
+    #[test]
+    fn new_workers_replace_gone_ones_oldest_first() {
+        let mut pool = Pool::default();
+        assert_eq!(pool.take_replaced(), None);
+        for n in 0..PENDING_REPLACEMENTS + 2 {
+            pool.worker_gone(&format!("w{n}"));
+        }
+        // The two oldest fell off
+        assert_eq!(pool.take_replaced().as_deref(), Some("w2"));
+        assert_eq!(pool.take_replaced().as_deref(), Some("w3"));
+        assert_eq!(pool.replaced.len(), PENDING_REPLACEMENTS - 2);
+    }
+
+    #[test]
+    fn restricted_callers_only_see_their_own_sessions() {
+        let mut headers = RestateHeaderMap::default();
+        assert!(check_owner(&headers, Some("alice")).is_ok());
+
+        headers.insert(auth::OWNER_HEADER, "alice".to_string());
+        assert!(check_owner(&headers, Some("alice")).is_ok());
+        let error = format!("{:?}", check_owner(&headers, Some("bob")).unwrap_err());
+        assert!(error.contains("404"), "{error}");
+        assert!(check_owner(&headers, None).is_err());
+    }
+
+    fn usage(rss_bytes: u64) -> ResourceUsage {
+        ResourceUsage {
+            rss_bytes,
+            cpu_seconds: 0.0,
+            open_fds: None,
+        }
+    }
+
+    #[test]
+    fn only_available_sessions_are_alive() {
+        let mut session = Session {
+            available: true,
+            ..Default::default()
+        };
+        assert!(session.check_alive().is_ok());
+        session.available = false;
+        assert!(session.check_alive().is_err());
+        session.failure = Some("worker_lost".to_string());
+        let error = format!("{:?}", session.check_alive().unwrap_err());
+        assert!(error.contains("Session failed: worker_lost"), "{error}");
+    }
+
+    #[test]
+    fn sessions_expire_once_their_ttl_has_passed() {
+        let session = Session {
+            available: true,
+            created_at: 1000,
+            ..Default::default()
+        };
+        assert!(!session.expired(1000, 60));
+        assert!(!session.expired(1059, 60));
+        assert!(session.expired(1060, 60));
+        // Recorded before creation times were, nothing says it is still young
+        assert!(Session::default().expired(1000, 60));
+    }
+
+    #[test]
+    fn retire_reason_checks_each_recycle_limit() {
+        let recycle = RecycleConfig {
+            max_sessions: 3,
+            max_age_secs: Some(60),
+            memory_bytes: Some(1000),
+        };
+        let mut worker = Worker {
+            started_at: 1000,
+            sessions_served: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            retire_reason(&worker, &recycle, Some(&usage(10)), 1059),
+            None
+        );
+        assert_eq!(
+            retire_reason(&worker, &recycle, Some(&usage(10)), 1060),
+            Some("max_age")
+        );
+        assert_eq!(
+            retire_reason(&worker, &recycle, Some(&usage(1001)), 1000),
+            Some("memory")
+        );
+        // Without a sample the memory limit can't apply
+        assert_eq!(retire_reason(&worker, &recycle, None, 1000), None);
+        worker.sessions_served = 3;
+        assert_eq!(
+            retire_reason(&worker, &recycle, None, 1000),
+            Some("max_sessions")
+        );
+    }
+
+    #[test]
+    fn retire_reason_skips_unset_limits() {
+        let recycle = RecycleConfig {
+            max_sessions: 0,
+            max_age_secs: None,
+            memory_bytes: None,
+        };
+        let worker = Worker {
+            started_at: 1,
+            sessions_served: 1000,
+            ..Default::default()
+        };
+        assert_eq!(
+            retire_reason(&worker, &recycle, Some(&usage(u64::MAX)), i64::MAX),
+            None
+        );
+        // Workers recorded before started_at never age out
+        let old = Worker::default();
+        let recycle = RecycleConfig {
+            max_age_secs: Some(1),
+            ..recycle
+        };
+        assert_eq!(retire_reason(&old, &recycle, None, 1_000_000), None);
     }
-    None
 }
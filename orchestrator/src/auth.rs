@@ -0,0 +1,534 @@
+use crate::config::AuthConfig;
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{StatusCode, header::AUTHORIZATION, request::Parts};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+// Carries the shared secret on Axum -> Restate ingress calls
+pub const INGRESS_SECRET_HEADER: &str = "x-orchestrator-secret";
+// Identify the authenticated caller to the Restate handlers, trusted because the ingress call
+// is signed
+pub const SUBJECT_HEADER: &str = "x-orchestrator-subject";
+pub const API_KEY_ID_HEADER: &str = "x-orchestrator-api-key-id";
+// Set when the caller may only see the sessions of this user
+pub const OWNER_HEADER: &str = "x-orchestrator-owner";
+
+// How long a fetched JWKS is used before it is fetched again, so rotated keys are picked up
+const JWKS_TTL: Duration = Duration::from_secs(600);
+// A token naming a key the JWKS doesn't hold refetches it at most this often, made up kids
+// can't hammer the identity provider
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "sessions:create")]
+    SessionsCreate,
+    #[serde(rename = "sessions:read")]
+    SessionsRead,
+    #[serde(rename = "sessions:delete")]
+    SessionsDelete,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub fn parse(raw: &str) -> Option<Scope> {
+        match raw {
+            "sessions:create" => Some(Scope::SessionsCreate),
+            "sessions:read" => Some(Scope::SessionsRead),
+            "sessions:delete" => Some(Scope::SessionsDelete),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::SessionsCreate => "sessions:create",
+            Scope::SessionsRead => "sessions:read",
+            Scope::SessionsDelete => "sessions:delete",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+// Static key entry, loaded from the api keys file
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub key: String,
+    pub scopes: Vec<Scope>,
+}
+
+// Identity attached to every authenticated request
+#[derive(Clone, Debug)]
+pub struct Principal {
+    // `sub` claim for JWTs, `None` for static keys and anonymous access
+    pub subject: Option<String>,
+    pub api_key_id: Option<String>,
+    pub scopes: Vec<Scope>,
+}
+
+impl Principal {
+    // Used when no authentication is configured, keeps the API open as before
+    pub fn anonymous() -> Self {
+        Principal {
+            subject: None,
+            api_key_id: None,
+            scopes: vec![Scope::Admin],
+        }
+    }
+
+    pub fn has(&self, scope: Scope) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| *s == scope || *s == Scope::Admin)
+    }
+
+    // The user whose sessions alone this principal may see, None when it may see them all.
+    // Only JWT subjects own sessions, static keys act for the whole deployment.
+    pub fn owner(&self) -> Option<&str> {
+        if self.has(Scope::Admin) {
+            return None;
+        }
+        self.subject.as_deref()
+    }
+
+    pub fn require(&self, scope: Scope) -> Result<(), (StatusCode, String)> {
+        if self.has(scope) {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::FORBIDDEN,
+                format!("Missing required scope: {}", scope.as_str()),
+            ))
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Principal>().cloned().ok_or((
+            StatusCode::UNAUTHORIZED,
+            "Request was not authenticated".to_string(),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    // OIDC style space separated scope list
+    #[serde(default)]
+    scope: String,
+}
+
+// The JWKS tokens are checked against, refetched from `source` when stale
+struct Jwks {
+    // File path or http(s) URL, None for a set handed in as is
+    source: Option<String>,
+    cached: RwLock<CachedJwks>,
+    ttl: Duration,
+    min_refresh: Duration,
+}
+
+struct CachedJwks {
+    set: JwkSet,
+    fetched_at: Instant,
+}
+
+impl Jwks {
+    fn new(source: Option<String>, set: JwkSet) -> Self {
+        Jwks {
+            source,
+            cached: RwLock::new(CachedJwks {
+                set,
+                fetched_at: Instant::now(),
+            }),
+            ttl: JWKS_TTL,
+            min_refresh: JWKS_MIN_REFRESH,
+        }
+    }
+
+    // The key a token was signed with. Without a kid the key is only unambiguous when the set
+    // holds a single one.
+    async fn key_for(&self, kid: Option<&str>) -> Result<Jwk, String> {
+        self.refresh_older_than(self.ttl).await;
+        if let Some(jwk) = find_key(&self.cached.read().await.set, kid)? {
+            return Ok(jwk);
+        }
+        // The provider may have rotated in a key since the last fetch
+        if kid.is_some()
+            && self.refresh_older_than(self.min_refresh).await
+            && let Some(jwk) = find_key(&self.cached.read().await.set, kid)?
+        {
+            return Ok(jwk);
+        }
+        Err("No matching key in JWKS".to_string())
+    }
+
+    // Refetches the set when it is older than `age`, a failed fetch keeps the old one.
+    // Returns whether it fetched.
+    async fn refresh_older_than(&self, age: Duration) -> bool {
+        let Some(source) = &self.source else {
+            return false;
+        };
+        if self.cached.read().await.fetched_at.elapsed() < age {
+            return false;
+        }
+        let mut cached = self.cached.write().await;
+        // Another request may have refreshed it while this one waited
+        if cached.fetched_at.elapsed() < age {
+            return false;
+        }
+        cached.fetched_at = Instant::now();
+        match load_jwks(source).await {
+            Ok(set) => cached.set = set,
+            Err(e) => tracing::warn!(error = %format!("{e:#}"), "JWKS refresh failed"),
+        }
+        true
+    }
+}
+
+fn find_key(set: &JwkSet, kid: Option<&str>) -> Result<Option<Jwk>, String> {
+    match kid {
+        Some(kid) => Ok(set.find(kid).cloned()),
+        None if set.keys.len() == 1 => Ok(set.keys.first().cloned()),
+        None => Err("Token has no kid and the JWKS holds several keys".to_string()),
+    }
+}
+
+// Algorithms a key may verify: the one its `alg` names, otherwise those of its key type.
+// Pinned so a token can't pick an algorithm the key wasn't meant for.
+fn allowed_algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    if let Some(alg) = jwk.common.key_algorithm {
+        return Algorithm::from_str(&alg.to_string()).into_iter().collect();
+    }
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(ec) => match ec.curve {
+            EllipticCurve::P256 => vec![Algorithm::ES256],
+            EllipticCurve::P384 => vec![Algorithm::ES384],
+            _ => Vec::new(),
+        },
+        AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+        AlgorithmParameters::OctetKey(_) => {
+            vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Authenticator {
+    api_keys: HashMap<String, ApiKey>,
+    jwks: Option<Jwks>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Authenticator {
+    pub fn new(api_keys: Vec<ApiKey>, jwks: Option<JwkSet>) -> Self {
+        Authenticator {
+            api_keys: api_keys.into_iter().map(|k| (k.key.clone(), k)).collect(),
+            jwks: jwks.map(|set| Jwks::new(None, set)),
+            issuer: None,
+            audience: None,
+        }
+    }
+
+    pub fn with_issuer(mut self, issuer: Option<String>) -> Self {
+        self.issuer = issuer;
+        self
+    }
+
+    pub fn with_audience(mut self, audience: Option<String>) -> Self {
+        self.audience = audience;
+        self
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwks.is_some()
+    }
+
+    // Loads the API keys file and the JWKS (file path or http(s) URL) when configured
+    pub async fn from_config(config: &AuthConfig) -> anyhow::Result<Self> {
+        let api_keys: Vec<ApiKey> = match &config.api_keys_file {
+            Some(path) => serde_json::from_slice(&tokio::fs::read(path).await?)?,
+            None => Vec::new(),
+        };
+        let jwks = match &config.jwks {
+            Some(source) => Some(Jwks::new(Some(source.clone()), load_jwks(source).await?)),
+            None => None,
+        };
+        Ok(Authenticator {
+            jwks,
+            ..Authenticator::new(api_keys, None)
+        }
+        .with_issuer(config.jwt_issuer.clone())
+        .with_audience(config.jwt_audience.clone()))
+    }
+
+    pub async fn authenticate(&self, token: &str) -> Result<Principal, String> {
+        if let Some(key) = self.api_keys.get(token) {
+            return Ok(Principal {
+                subject: None,
+                api_key_id: Some(key.id.clone()),
+                scopes: key.scopes.clone(),
+            });
+        }
+
+        let jwks = self.jwks.as_ref().ok_or("Unknown API key".to_string())?;
+        let header = decode_header(token).map_err(|e| format!("Invalid token: {}", e))?;
+        let jwk = jwks.key_for(header.kid.as_deref()).await?;
+        let algorithms = allowed_algorithms(&jwk);
+        if !algorithms.contains(&header.alg) {
+            return Err(format!(
+                "Algorithm {:?} not allowed for this key",
+                header.alg
+            ));
+        }
+        let key = DecodingKey::from_jwk(&jwk).map_err(|e| format!("Unusable JWK: {}", e))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.algorithms = algorithms;
+        match &self.audience {
+            Some(aud) => validation.set_audience(&[aud]),
+            None => validation.validate_aud = false,
+        }
+        if let Some(iss) = &self.issuer {
+            validation.set_issuer(&[iss]);
+        }
+
+        let claims = decode::<Claims>(token, &key, &validation)
+            .map_err(|e| format!("Invalid token: {}", e))?
+            .claims;
+
+        Ok(Principal {
+            subject: Some(claims.sub),
+            api_key_id: None,
+            scopes: claims
+                .scope
+                .split_whitespace()
+                .filter_map(Scope::parse)
+                .collect(),
+        })
+    }
+}
+
+pub async fn load_jwks(source: &str) -> anyhow::Result<JwkSet> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        Ok(Client::new().get(source).send().await?.json().await?)
+    } else {
+        Ok(serde_json::from_slice(&tokio::fs::read(source).await?)?)
+    }
+}
+
+// Resolves the bearer token into a `Principal` extension for the handlers
+pub async fn authenticate(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !auth.enabled() {
+        request.extensions_mut().insert(Principal::anonymous());
+        return next.run(request).await;
+    }
+
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let Some(token) = token else {
+        return (StatusCode::UNAUTHORIZED, "Missing bearer token").into_response();
+    };
+
+    match auth.authenticate(token).await {
+        Ok(principal) => {
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        Err(e) => (StatusCode::UNAUTHORIZED, e).into_response(),
+    }
+}
+
+// Compares secrets without short-circuiting on the first differing byte
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+
+    const KEY_A: &str = include_str!("../tests/fixtures/jwt-a.pem");
+    const KEY_B: &str = include_str!("../tests/fixtures/jwt-b.pem");
+    const JWKS: &str = include_str!("../tests/fixtures/jwks.json");
+
+    // The fixture set, or only the keys named
+    fn jwks(kids: &[&str]) -> JwkSet {
+        let mut set: JwkSet = serde_json::from_str(JWKS).unwrap();
+        set.keys
+            .retain(|k| kids.contains(&k.common.key_id.as_deref().unwrap()));
+        set
+    }
+
+    fn token(pem: &str, alg: Algorithm, kid: Option<&str>, claims: serde_json::Value) -> String {
+        let mut header = Header::new(alg);
+        header.kid = kid.map(str::to_string);
+        let exp = jsonwebtoken::get_current_timestamp() + 600;
+        let mut claims = claims;
+        claims["exp"] = json!(exp);
+        encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn claims() -> serde_json::Value {
+        json!({
+            "sub": "alice",
+            "scope": "sessions:read openid sessions:create",
+            "iss": "https://issuer.example",
+            "aud": "orchestrator",
+        })
+    }
+
+    fn authenticator(set: JwkSet) -> Authenticator {
+        Authenticator::new(vec![], Some(set))
+            .with_issuer(Some("https://issuer.example".to_string()))
+            .with_audience(Some("orchestrator".to_string()))
+    }
+
+    #[test]
+    fn only_non_admin_subjects_own_sessions() {
+        let principal = |subject: Option<&str>, scopes: Vec<Scope>| Principal {
+            subject: subject.map(str::to_string),
+            api_key_id: None,
+            scopes,
+        };
+        let user = principal(Some("alice"), vec![Scope::SessionsRead]);
+        assert_eq!(user.owner(), Some("alice"));
+        assert_eq!(principal(Some("alice"), vec![Scope::Admin]).owner(), None);
+        assert_eq!(principal(None, vec![Scope::SessionsRead]).owner(), None);
+        assert_eq!(Principal::anonymous().owner(), None);
+    }
+
+    #[tokio::test]
+    async fn api_keys_carry_their_scopes() {
+        let auth = Authenticator::new(
+            vec![ApiKey {
+                id: "ci".to_string(),
+                key: "secret".to_string(),
+                scopes: vec![Scope::SessionsRead],
+            }],
+            None,
+        );
+        let principal = auth.authenticate("secret").await.unwrap();
+        assert_eq!(principal.api_key_id.as_deref(), Some("ci"));
+        assert!(principal.has(Scope::SessionsRead));
+        assert!(!principal.has(Scope::SessionsDelete));
+        assert!(auth.authenticate("other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn tokens_resolve_to_their_subject_and_known_scopes() {
+        let auth = authenticator(jwks(&["a", "b"]));
+        let principal = auth
+            .authenticate(&token(KEY_A, Algorithm::RS256, Some("a"), claims()))
+            .await
+            .unwrap();
+        assert_eq!(principal.subject.as_deref(), Some("alice"));
+        assert_eq!(
+            principal.scopes,
+            [Scope::SessionsRead, Scope::SessionsCreate]
+        );
+
+        // Signed with the other key than the kid names
+        let forged = token(KEY_B, Algorithm::RS256, Some("a"), claims());
+        assert!(auth.authenticate(&forged).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_the_wrong_audience_or_issuer() {
+        let auth = authenticator(jwks(&["a"]));
+        let mut wrong_aud = claims();
+        wrong_aud["aud"] = json!("someone-else");
+        let mut wrong_iss = claims();
+        wrong_iss["iss"] = json!("https://evil.example");
+        for claims in [wrong_aud, wrong_iss] {
+            let token = token(KEY_A, Algorithm::RS256, Some("a"), claims);
+            assert!(auth.authenticate(&token).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn tokens_without_kid_need_a_single_key_set() {
+        let no_kid = token(KEY_A, Algorithm::RS256, None, claims());
+        assert!(
+            authenticator(jwks(&["a"]))
+                .authenticate(&no_kid)
+                .await
+                .is_ok()
+        );
+        let err = authenticator(jwks(&["a", "b"]))
+            .authenticate(&no_kid)
+            .await
+            .unwrap_err();
+        assert!(err.contains("no kid"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn the_algorithm_is_pinned_to_the_key() {
+        // The fixture JWKs declare RS256
+        let token = token(KEY_A, Algorithm::RS512, Some("a"), claims());
+        let err = authenticator(jwks(&["a"]))
+            .authenticate(&token)
+            .await
+            .unwrap_err();
+        assert!(err.contains("not allowed"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn unknown_kids_refetch_the_jwks() {
+        let path = std::env::temp_dir().join(format!("jwks-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, serde_json::to_vec(&jwks(&["a"])).unwrap()).unwrap();
+        let config = AuthConfig {
+            jwks: Some(path.display().to_string()),
+            ..Default::default()
+        };
+        let mut auth = Authenticator::from_config(&config).await.unwrap();
+        let token = token(KEY_B, Algorithm::RS256, Some("b"), claims());
+        assert!(auth.authenticate(&token).await.is_err());
+
+        // The provider rotates key b in
+        std::fs::write(&path, serde_json::to_vec(&jwks(&["a", "b"])).unwrap()).unwrap();
+        // Still within the refresh interval of the last fetch
+        assert!(auth.authenticate(&token).await.is_err());
+        auth.jwks.as_mut().unwrap().min_refresh = Duration::ZERO;
+        assert!(auth.authenticate(&token).await.is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+use crate::config::LimitsConfig;
+use anyhow::Context;
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+// Where cgroups v2 is mounted, /proc/<pid>/cgroup paths are relative to it
+const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+
+// Period cpu.max quotas are expressed against, the kernel default
+const CPU_PERIOD_US: u64 = 100_000;
+
+// Each worker's cgroup, under the delegated root
+pub fn cgroup_dir(limits: &LimitsConfig, worker_id: &str) -> Option<PathBuf> {
+    let root = limits.cgroup_root.as_ref()?;
+    limits
+        .needs_cgroup()
+        .then(|| root.join(format!("worker-{}", worker_id)))
+}
+
+// Where the kernel put `pid`, if that is the worker's own cgroup. Lets workers found after
+// a restart be tied to their cgroup whatever the config says now.
+pub fn of_process(pid: i32, worker_id: &str) -> Option<PathBuf> {
+    let raw = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    // cgroups v2 has a single "0::<path>" line
+    let path = raw.lines().find_map(|line| line.strip_prefix("0::"))?;
+    let dir = Path::new(CGROUP_MOUNT).join(path.trim_start_matches('/'));
+    dir.ends_with(format!("worker-{}", worker_id))
+        .then_some(dir)
+}
+
+// Creates the worker's cgroup and makes `command` enter it and set its rlimits between fork
+// and exec, so nothing the worker starts escapes the limits. Returns the cgroup, callers keep
+// it with the worker since the config it came from may change.
+pub fn apply(
+    command: &mut Command,
+    limits: &LimitsConfig,
+    worker_id: &str,
+) -> anyhow::Result<Option<PathBuf>> {
+    let dir = cgroup_dir(limits, worker_id);
+    let procs = match &dir {
+        Some(dir) => Some(create(dir, limits)?),
+        None => None,
+    };
+    let open_files = limits.open_files;
+    if procs.is_none() && open_files.is_none() {
+        return Ok(dir);
+    }
+    // Only async-signal-safe calls on fds opened up front in here
+    let enter = move || {
+        if let Some(procs) = &procs {
+            // "0" moves the writing process
+            if unsafe { libc::write(procs.as_raw_fd(), b"0".as_ptr().cast(), 1) } < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        if let Some(n) = open_files {
+            let limit = libc::rlimit {
+                rlim_cur: n as libc::rlim_t,
+                rlim_max: n as libc::rlim_t,
+            };
+            if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    };
+    unsafe {
+        command.pre_exec(enter);
+    }
+    Ok(dir)
+}
+
+// Returns cgroup.procs opened for writing
+fn create(dir: &Path, limits: &LimitsConfig) -> anyhow::Result<File> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Error creating cgroup {}", dir.display()))?;
+    let mut settings = Vec::new();
+    if let Some(bytes) = limits.memory_bytes {
+        settings.push(("memory.max", bytes.to_string()));
+        // An OOM kill takes the whole browser down instead of leaving it half alive
+        settings.push(("memory.oom.group", "1".to_string()));
+    }
+    if let Some(cpus) = limits.cpus {
+        settings.push(("cpu.max", cpu_max(cpus)));
+    }
+    if let Some(pids) = limits.pids_limit {
+        settings.push(("pids.max", pids.to_string()));
+    }
+    for (file, value) in settings {
+        std::fs::write(dir.join(file), &value)
+            .with_context(|| format!("Error setting {} to {} in {}", file, value, dir.display()))?;
+    }
+    File::options()
+        .write(true)
+        .open(dir.join("cgroup.procs"))
+        .with_context(|| format!("Error opening {}/cgroup.procs", dir.display()))
+}
+
+fn cpu_max(cpus: f64) -> String {
+    let quota = ((cpus * CPU_PERIOD_US as f64) as u64).max(1000);
+    format!("{} {}", quota, CPU_PERIOD_US)
+}
+
+// Whether the kernel OOM killed anything in the worker's cgroup
+pub fn oom_killed(dir: &Path) -> bool {
+    std::fs::read_to_string(dir.join("memory.events"))
+        .ok()
+        .and_then(|events| {
+            events
+                .lines()
+                .find_map(|line| line.strip_prefix("oom_kill "))
+                .and_then(|n| n.trim().parse::<u64>().ok())
+        })
+        .is_some_and(|n| n > 0)
+}
+
+// Gives the worker a moment to exit after SIGTERM, kills what is left and removes the cgroup
+pub async fn remove(dir: &Path) -> anyhow::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for _ in 0..40 {
+        let events = std::fs::read_to_string(dir.join("cgroup.events")).unwrap_or_default();
+        if !events.contains("populated 1") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    // cgroup.kill needs Linux 5.14, older kernels leave stragglers for the rmdir retries
+    let _ = std::fs::write(dir.join("cgroup.kill"), "1");
+    let mut attempts = 0;
+    loop {
+        match std::fs::remove_dir(dir) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(_) if attempts < 20 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Error removing cgroup {}", dir.display()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A plain directory stands in for the cgroup root, the kernel isn't needed to check what
+    // gets written
+    fn limits() -> (LimitsConfig, PathBuf) {
+        let root = std::env::temp_dir().join(format!("cgroup-{}", uuid::Uuid::new_v4()));
+        let limits = LimitsConfig {
+            cgroup_root: Some(root.clone()),
+            memory_bytes: Some(512 * 1024 * 1024),
+            cpus: Some(1.5),
+            pids_limit: Some(256),
+            open_files: None,
+        };
+        (limits, root)
+    }
+
+    #[test]
+    fn writes_the_configured_limits() {
+        let (limits, root) = limits();
+        let dir = cgroup_dir(&limits, "w1").unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cgroup.procs"), "").unwrap();
+        create(&dir, &limits).unwrap();
+
+        let read = |file: &str| std::fs::read_to_string(dir.join(file)).unwrap();
+        assert_eq!(read("memory.max"), "536870912");
+        assert_eq!(read("memory.oom.group"), "1");
+        assert_eq!(read("cpu.max"), "150000 100000");
+        assert_eq!(read("pids.max"), "256");
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn reads_oom_kills_from_memory_events() {
+        let (limits, root) = limits();
+        let dir = cgroup_dir(&limits, "w2").unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(!oom_killed(&dir));
+
+        std::fs::write(
+            dir.join("memory.events"),
+            "low 0\nhigh 0\nmax 3\noom 1\noom_kill 0\n",
+        )
+        .unwrap();
+        assert!(!oom_killed(&dir));
+        std::fs::write(
+            dir.join("memory.events"),
+            "low 0\nhigh 0\nmax 7\noom 2\noom_kill 1\n",
+        )
+        .unwrap();
+        assert!(oom_killed(&dir));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn open_files_limit_applies_to_the_worker() {
+        let limits = LimitsConfig {
+            open_files: Some(256),
+            ..Default::default()
+        };
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -n"]);
+        apply(&mut command, &limits, "w4").unwrap();
+        let output = command.output().await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "256");
+    }
+
+    #[test]
+    fn no_cgroup_without_cgroup_limits() {
+        let (mut limits, _) = limits();
+        limits.memory_bytes = None;
+        limits.cpus = None;
+        limits.pids_limit = None;
+        limits.open_files = Some(1024);
+        assert!(cgroup_dir(&limits, "w3").is_none());
+    }
+}
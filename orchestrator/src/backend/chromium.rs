@@ -0,0 +1,553 @@
+use super::local::{ProcessHandle, terminate};
+use super::{ResourceUsage, SpawnRequest, SpawnedWorker, WorkerBackend, WorkerRef, cgroup, usage};
+use crate::auth::constant_time_eq;
+use crate::config::ConfigHandle;
+use crate::logs::WORKER_LOGS;
+use anyhow::{Context, anyhow, bail};
+use async_trait::async_trait;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header::AUTHORIZATION};
+use axum::routing::get;
+use axum::{Json, Router};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+use tokio::time::Instant;
+
+// Chromium writes its debugging port here, inside the user-data-dir
+const PORT_FILE: &str = "DevToolsActivePort";
+
+// One <session id>.json per session the worker serves, inside the worker's directory
+const SESSIONS_DIR: &str = "sessions";
+
+// Headless Chromium launched directly, without steel-browser. Chromium only speaks CDP, so
+// the orchestrator serves the session API the pool expects from a shim on
+// `chromium.shim_listen`, answering from CDP /json/version. Everything the shim needs lives
+// in the worker's directory, so it keeps working across orchestrator restarts.
+pub struct ChromiumBackend {
+    config: ConfigHandle,
+    shim_base: String,
+}
+
+// Written next to the profile at spawn
+#[derive(Deserialize, Serialize)]
+struct WorkerFile {
+    pid: i32,
+    token: String,
+}
+
+// Written by the shim when the pool creates a session
+#[derive(Clone, Deserialize, Serialize)]
+struct SessionFile {
+    id: String,
+    created_at: i64,
+    user: String,
+}
+
+#[derive(Deserialize)]
+struct CreateSession {
+    // The id the pool assigned, a fresh one when unset
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+    user: String,
+}
+
+impl ChromiumBackend {
+    pub fn new(config: ConfigHandle) -> anyhow::Result<Self> {
+        let listen = config.get().chromium.shim_listen;
+        let listener = std::net::TcpListener::bind(listen)
+            .with_context(|| format!("Error binding chromium shim on {}", listen))?;
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let shim_base = format!("http://{}", listener.local_addr()?);
+        let app = shim_router(Shim {
+            config: config.clone(),
+            client: Client::builder().timeout(Duration::from_secs(2)).build()?,
+        });
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!(error = %e, "chromium shim stopped");
+            }
+        });
+        Ok(ChromiumBackend { config, shim_base })
+    }
+}
+
+#[async_trait]
+impl WorkerBackend for ChromiumBackend {
+    fn name(&self) -> &'static str {
+        "chromium"
+    }
+
+    async fn spawn(&self, request: &SpawnRequest) -> anyhow::Result<SpawnedWorker> {
+        let config = self.config.get();
+        let chromium = &config.chromium;
+        let dir = chromium.data_dir.join(&request.worker_id);
+        let profile = dir.join("profile");
+        tokio::fs::create_dir_all(&profile)
+            .await
+            .with_context(|| format!("Error creating {}", profile.display()))?;
+        // worker.json holds the worker's token
+        tokio::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).await?;
+
+        // Port 0 lets Chromium pick a free port and report it in PORT_FILE
+        let mut command = Command::new(&chromium.binary);
+        command
+            .args(&chromium.flags)
+            .arg("--remote-debugging-port=0")
+            .arg(format!("--user-data-dir={}", profile.display()))
+            .arg("about:blank")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let cgroup = cgroup::apply(&mut command, &config.limits, &request.worker_id)?;
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Error starting {}", chromium.binary))?;
+        WORKER_LOGS.capture(&request.worker_id, &mut child);
+        let pid = child
+            .id()
+            .ok_or_else(|| anyhow!("{} exited immediately", chromium.binary))?
+            as i32;
+        let worker_file = WorkerFile {
+            pid,
+            token: request.token.clone(),
+        };
+        tokio::fs::write(dir.join("worker.json"), serde_json::to_vec(&worker_file)?).await?;
+
+        let deadline = Instant::now() + chromium.startup_timeout();
+        loop {
+            if let Some(port) = read_port(&profile).await {
+                return Ok(SpawnedWorker {
+                    port: Some(port),
+                    handle: Some(ProcessHandle::encode(pid, cgroup.as_ref())),
+                    node: None,
+                });
+            }
+            if let Some(status) = child.try_wait()? {
+                abandon(&dir, cgroup.as_deref()).await;
+                bail!("{} exited during startup: {}", chromium.binary, status);
+            }
+            if Instant::now() >= deadline {
+                let _ = terminate(pid);
+                abandon(&dir, cgroup.as_deref()).await;
+                bail!(
+                    "{} did not open a debugging port within {}s",
+                    chromium.binary,
+                    chromium.startup_timeout_secs
+                );
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    async fn endpoint(&self, worker: &WorkerRef) -> anyhow::Result<String> {
+        Ok(format!("{}/workers/{}", self.shim_base, worker.id))
+    }
+
+    async fn stop(&self, worker: &WorkerRef) -> anyhow::Result<()> {
+        let config = self.config.get();
+        let handle = ProcessHandle::decode(worker, &config.limits);
+        let dir = config.chromium.data_dir.join(&worker.id);
+        if let Some(pid) = handle.pid
+            && is_browser(pid, &dir)
+        {
+            terminate(pid)?;
+        }
+        handle.remove_cgroup().await?;
+        match tokio::fs::remove_dir_all(&dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Error removing {}", dir.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn usage(&self, worker: &WorkerRef) -> Option<ResourceUsage> {
+        let config = self.config.get();
+        let handle = ProcessHandle::decode(worker, &config.limits);
+        // Once the browser exited its pid may be some other process's
+        let dir = config.chromium.data_dir.join(&worker.id);
+        if handle.pid.is_some_and(|pid| !is_browser(pid, &dir)) {
+            return None;
+        }
+        usage::sample(handle.cgroup.as_deref(), handle.pid)
+    }
+
+    async fn exit_reason(&self, worker: &WorkerRef) -> Option<String> {
+        ProcessHandle::decode(worker, &self.config.get().limits).oom_killed()
+    }
+
+    // Worker directories whose browser is still running
+    async fn discover(&self) -> anyhow::Result<Vec<WorkerRef>> {
+        let data_dir = self.config.get().chromium.data_dir.clone();
+        let mut entries = match tokio::fs::read_dir(&data_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut found = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(worker) = read_worker(&entry.path()).await else {
+                continue;
+            };
+            if !is_browser(worker.pid, &entry.path()) {
+                continue;
+            }
+            let id = entry.file_name().to_string_lossy().into_owned();
+            found.push(WorkerRef {
+                port: read_port(&entry.path().join("profile")).await,
+                token: worker.token,
+                handle: Some(ProcessHandle::encode(
+                    worker.pid,
+                    cgroup::of_process(worker.pid, &id).as_ref(),
+                )),
+                id,
+            });
+        }
+        Ok(found)
+    }
+
+    // Worker directories whose browser is gone, left behind by a previous run
+    async fn cleanup_orphans(&self) -> anyhow::Result<usize> {
+        let data_dir = self.config.get().chromium.data_dir.clone();
+        let mut entries = match tokio::fs::read_dir(&data_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let alive = read_worker(&entry.path())
+                .await
+                .is_some_and(|w| is_browser(w.pid, &entry.path()));
+            if !alive {
+                tokio::fs::remove_dir_all(entry.path()).await?;
+                tracing::info!(dir = %entry.path().display(), "removed orphaned chromium worker");
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+// Whether `pid` is the browser of the worker in `dir`, told by the profile on its command
+// line since the kernel reuses the pids of exited browsers
+fn is_browser(pid: i32, dir: &std::path::Path) -> bool {
+    let flag = format!("--user-data-dir={}", dir.join("profile").display());
+    std::fs::read(format!("/proc/{}/cmdline", pid))
+        .is_ok_and(|cmdline| cmdline.split(|b| *b == 0).any(|arg| arg == flag.as_bytes()))
+}
+
+async fn read_port(profile: &std::path::Path) -> Option<u16> {
+    let raw = tokio::fs::read_to_string(profile.join(PORT_FILE))
+        .await
+        .ok()?;
+    raw.lines().next()?.trim().parse().ok()
+}
+
+async fn read_worker(dir: &std::path::Path) -> Option<WorkerFile> {
+    let raw = tokio::fs::read(dir.join("worker.json")).await.ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+fn session_path(dir: &std::path::Path, session_id: &str) -> Option<PathBuf> {
+    // Session ids are uuids, anything else could escape the worker's directory
+    uuid::Uuid::parse_str(session_id).ok()?;
+    Some(dir.join(SESSIONS_DIR).join(format!("{}.json", session_id)))
+}
+
+async fn read_session(dir: &std::path::Path, session_id: &str) -> Option<SessionFile> {
+    let raw = tokio::fs::read(session_path(dir, session_id)?).await.ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+// Oldest first
+async fn read_sessions(dir: &std::path::Path) -> Vec<SessionFile> {
+    let mut sessions = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir(dir.join(SESSIONS_DIR)).await else {
+        return sessions;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if let Ok(raw) = tokio::fs::read(entry.path()).await
+            && let Ok(session) = serde_json::from_slice::<SessionFile>(&raw)
+        {
+            sessions.push(session);
+        }
+    }
+    sessions.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    sessions
+}
+
+async fn write_session(dir: &std::path::Path, session: &SessionFile) -> anyhow::Result<()> {
+    let path = session_path(dir, &session.id).ok_or_else(|| anyhow!("Invalid session id"))?;
+    tokio::fs::create_dir_all(dir.join(SESSIONS_DIR)).await?;
+    tokio::fs::write(path, serde_json::to_vec(session)?).await?;
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Shim {
+    config: ConfigHandle,
+    client: Client,
+}
+
+type ShimError = (StatusCode, String);
+
+impl Shim {
+    // The worker's directory, once the caller proved it holds the worker's token
+    async fn authorize(&self, worker_id: &str, headers: &HeaderMap) -> Result<PathBuf, ShimError> {
+        let dir = self.config.get().chromium.data_dir.join(worker_id);
+        // Worker ids are uuids, anything else could escape data_dir
+        if uuid::Uuid::parse_str(worker_id).is_err() {
+            return Err((StatusCode::NOT_FOUND, "Unknown worker".to_string()));
+        }
+        let worker = read_worker(&dir)
+            .await
+            .ok_or((StatusCode::NOT_FOUND, "Unknown worker".to_string()))?;
+        let presented = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if !worker.token.is_empty()
+            && !constant_time_eq(presented.as_bytes(), worker.token.as_bytes())
+        {
+            return Err((StatusCode::UNAUTHORIZED, "Invalid worker token".to_string()));
+        }
+        Ok(dir)
+    }
+
+    // CDP /json/version of the worker's browser
+    async fn version(&self, dir: &std::path::Path) -> Result<Value, ShimError> {
+        let port = read_port(&dir.join("profile")).await.ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Browser has no debugging port".to_string(),
+        ))?;
+        let unavailable = |e: reqwest::Error| (StatusCode::SERVICE_UNAVAILABLE, e.to_string());
+        self.client
+            .get(format!("http://127.0.0.1:{}/json/version", port))
+            .send()
+            .await
+            .map_err(unavailable)?
+            .json()
+            .await
+            .map_err(unavailable)
+    }
+}
+
+fn shim_router(shim: Shim) -> Router {
+    Router::new()
+        .route("/workers/{worker_id}/health", get(health))
+        .route("/workers/{worker_id}/status", get(status))
+        .route(
+            "/workers/{worker_id}/sessions",
+            axum::routing::post(create_session),
+        )
+        .route(
+            "/workers/{worker_id}/sessions/{session_id}",
+            get(get_session).delete(delete_session),
+        )
+        .with_state(shim)
+}
+
+// Leaves nothing behind of a browser that never came up, the pool has no worker to stop
+async fn abandon(dir: &std::path::Path, cgroup: Option<&std::path::Path>) {
+    if let Some(cgroup) = cgroup {
+        let _ = cgroup::remove(cgroup).await;
+    }
+    let _ = tokio::fs::remove_dir_all(dir).await;
+}
+
+// Same shape steel-browser returns for a session
+fn session_body(session: &SessionFile, version: &Value) -> Value {
+    json!({
+        "id": session.id,
+        "created_at": session.created_at,
+        "data": { "user": session.user },
+        "websocket_url": version["webSocketDebuggerUrl"],
+        "browser": version["Browser"],
+    })
+}
+
+async fn health(
+    State(shim): State<Shim>,
+    Path(worker_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<&'static str, ShimError> {
+    let dir = shim.authorize(&worker_id, &headers).await?;
+    shim.version(&dir).await?;
+    Ok("ok")
+}
+
+// Serves both the pool's status check and its session listing, so it carries both shapes
+async fn status(
+    State(shim): State<Shim>,
+    Path(worker_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ShimError> {
+    let dir = shim.authorize(&worker_id, &headers).await?;
+    // Workers report their first session, like steel-browser. An idle warm worker reports an
+    // empty id, the pool adopts it rather than taking it for dead.
+    let session = read_sessions(&dir).await.into_iter().next();
+    let version = shim.version(&dir).await;
+    let available = version.is_ok();
+    let mut body = match &session {
+        Some(session) => session_body(session, &version.unwrap_or_default()),
+        None => json!({ "id": "", "created_at": 0, "data": { "user": "" } }),
+    };
+    body["session_id"] = body["id"].clone();
+    body["available"] = json!(available);
+    Ok(Json(body))
+}
+
+async fn create_session(
+    State(shim): State<Shim>,
+    Path(worker_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CreateSession>,
+) -> Result<Json<Value>, ShimError> {
+    let dir = shim.authorize(&worker_id, &headers).await?;
+    let version = shim.version(&dir).await?;
+    let id = match request.session_id {
+        Some(id) if uuid::Uuid::parse_str(&id).is_err() => {
+            return Err((StatusCode::BAD_REQUEST, "Invalid session id".to_string()));
+        }
+        Some(id) => id,
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+    let session = SessionFile {
+        id,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64,
+        user: request.user,
+    };
+    write_session(&dir, &session)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    Ok(Json(session_body(&session, &version)))
+}
+
+async fn get_session(
+    State(shim): State<Shim>,
+    Path((worker_id, session_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ShimError> {
+    let dir = shim.authorize(&worker_id, &headers).await?;
+    let session = read_session(&dir, &session_id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Session not found".to_string()))?;
+    let version = shim.version(&dir).await.unwrap_or_default();
+    Ok(Json(session_body(&session, &version)))
+}
+
+// The browser itself goes away when the pool stops the worker
+async fn delete_session(
+    State(shim): State<Shim>,
+    Path((worker_id, session_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ShimError> {
+    let dir = shim.authorize(&worker_id, &headers).await?;
+    if let Some(path) = session_path(&dir, &session_id) {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_the_process_using_the_profile_is_the_browser() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("worker");
+        // $0 of the script, so it shows up on the command line like Chromium's flag
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 5; true"])
+            .arg(format!("--user-data-dir={}", dir.join("profile").display()))
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let pid = child.id().unwrap() as i32;
+        assert!(is_browser(pid, &dir));
+        assert!(!is_browser(pid, &dir.with_file_name("another-worker")));
+
+        child.kill().await.unwrap();
+        child.wait().await.unwrap();
+        assert!(!is_browser(pid, &dir));
+    }
+
+    #[tokio::test]
+    async fn idle_worker_reports_no_session() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let worker_id = uuid::Uuid::new_v4().to_string();
+        let dir = data_dir.path().join(&worker_id);
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let worker = WorkerFile {
+            pid: 0,
+            token: String::new(),
+        };
+        tokio::fs::write(
+            dir.join("worker.json"),
+            serde_json::to_vec(&worker).unwrap(),
+        )
+        .await
+        .unwrap();
+        let mut config = crate::config::Config::default();
+        config.chromium.data_dir = data_dir.path().to_path_buf();
+        let app = shim_router(Shim {
+            config: ConfigHandle::new(config),
+            client: Client::new(),
+        });
+
+        let request = axum::http::Request::get(format!("/workers/{}/status", worker_id))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], "");
+        assert_eq!(body["session_id"], "");
+    }
+
+    #[tokio::test]
+    async fn sessions_on_one_worker_are_kept_apart() {
+        let dir = std::env::temp_dir().join(format!("chromium-{}", uuid::Uuid::new_v4()));
+        let session = |id: &str, created_at| SessionFile {
+            id: id.to_string(),
+            created_at,
+            user: "u".to_string(),
+        };
+        let first = "00000000-0000-4000-8000-000000000001";
+        let second = "00000000-0000-4000-8000-000000000002";
+        write_session(&dir, &session(second, 20)).await.unwrap();
+        write_session(&dir, &session(first, 10)).await.unwrap();
+
+        let ids =
+            |sessions: Vec<SessionFile>| sessions.into_iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(ids(read_sessions(&dir).await), [first, second]);
+        assert_eq!(read_session(&dir, second).await.unwrap().created_at, 20);
+        assert!(read_session(&dir, "../worker").await.is_none());
+        assert!(write_session(&dir, &session("../worker", 0)).await.is_err());
+
+        tokio::fs::remove_file(session_path(&dir, first).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(ids(read_sessions(&dir).await), [second]);
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,453 @@
+use super::{
+    MANAGED_LABEL, OOM_KILLED, ResourceUsage, SpawnRequest, SpawnedWorker, USER_LABEL,
+    WORKER_LABEL, WorkerBackend, WorkerRef, check, worker_name,
+};
+use crate::config::{ConfigHandle, ContainerConfig};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode};
+use serde_json::{Value, json};
+use std::path::Path;
+
+// Docker Engine API version, also served by Podman's compat API
+const API_VERSION: &str = "v1.41";
+
+// steel-browser in a container, created through the Docker or Podman API on a Unix socket.
+// Without a network the container port is published on 127.0.0.1, with one the worker is
+// reached by container name on that network.
+pub struct ContainerBackend {
+    config: ConfigHandle,
+    client: Client,
+}
+
+impl ContainerBackend {
+    pub fn new(config: ConfigHandle) -> anyhow::Result<Self> {
+        let socket = config.get().container.socket.clone();
+        Ok(ContainerBackend {
+            config,
+            client: engine_client(&socket)?,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        // The host is ignored on a Unix socket
+        format!("http://docker/{}{}", API_VERSION, path)
+    }
+
+    async fn create(&self, name: &str, body: &Value) -> anyhow::Result<Response> {
+        Ok(self
+            .client
+            .post(self.url("/containers/create"))
+            .query(&[("name", name)])
+            .json(body)
+            .send()
+            .await?)
+    }
+
+    async fn pull(&self, image: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(self.url("/images/create"))
+            .query(&[("fromImage", image)])
+            .send()
+            .await?;
+        check(response, "pull image").await?;
+        Ok(())
+    }
+
+    async fn inspect(&self, id: &str) -> anyhow::Result<Value> {
+        let response = self
+            .client
+            .get(self.url(&format!("/containers/{}/json", id)))
+            .send()
+            .await?;
+        Ok(check(response, "inspect container").await?.json().await?)
+    }
+
+    async fn remove(&self, id: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .delete(self.url(&format!("/containers/{}", id)))
+            .query(&[("force", "true")])
+            .send()
+            .await?;
+        // Already gone is what we wanted
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(response, "remove container").await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl WorkerBackend for ContainerBackend {
+    fn name(&self) -> &'static str {
+        "container"
+    }
+
+    async fn spawn(&self, request: &SpawnRequest) -> anyhow::Result<SpawnedWorker> {
+        let config = self.config.get();
+        let name = worker_name(&request.worker_id);
+        let body = create_body(&config.container, request);
+
+        let mut response = self.create(&name, &body).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            // Image missing locally
+            self.pull(&config.container.image).await?;
+            response = self.create(&name, &body).await?;
+        }
+        let created: Value = check(response, "create container").await?.json().await?;
+        let id = created["Id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Container create response without Id"))?
+            .to_string();
+
+        let started = self
+            .client
+            .post(self.url(&format!("/containers/{}/start", id)))
+            .send()
+            .await;
+        if let Err(e) = async { check(started?, "start container").await }.await {
+            let _ = self.remove(&id).await;
+            return Err(e);
+        }
+
+        let port = match &config.container.network {
+            Some(_) => None,
+            None => {
+                let port = async {
+                    let info = self.inspect(&id).await?;
+                    published_port(&info, config.container.port)
+                }
+                .await;
+                match port {
+                    Ok(port) => Some(port),
+                    Err(e) => {
+                        let _ = self.remove(&id).await;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        Ok(SpawnedWorker {
+            port,
+            handle: Some(id),
+            node: None,
+        })
+    }
+
+    async fn endpoint(&self, worker: &WorkerRef) -> anyhow::Result<String> {
+        let config = self.config.get();
+        match &config.container.network {
+            Some(_) => Ok(format!(
+                "http://{}:{}",
+                worker_name(&worker.id),
+                config.container.port
+            )),
+            None => {
+                let port = worker
+                    .port
+                    .ok_or_else(|| anyhow!("Error fetching worker port"))?;
+                Ok(format!("http://127.0.0.1:{}", port))
+            }
+        }
+    }
+
+    async fn stop(&self, worker: &WorkerRef) -> anyhow::Result<()> {
+        let id = worker
+            .handle
+            .clone()
+            .unwrap_or_else(|| worker_name(&worker.id));
+        self.remove(&id).await
+    }
+
+    async fn usage(&self, worker: &WorkerRef) -> Option<ResourceUsage> {
+        let id = worker
+            .handle
+            .clone()
+            .unwrap_or_else(|| worker_name(&worker.id));
+        let response = self
+            .client
+            .get(self.url(&format!("/containers/{}/stats", id)))
+            .query(&[("stream", "false"), ("one-shot", "true")])
+            .send()
+            .await
+            .ok()?;
+        let stats: Value = check(response, "container stats")
+            .await
+            .ok()?
+            .json()
+            .await
+            .ok()?;
+        Some(ResourceUsage {
+            rss_bytes: stats["memory_stats"]["usage"].as_u64()?,
+            cpu_seconds: stats["cpu_stats"]["cpu_usage"]["total_usage"].as_u64()? as f64 / 1e9,
+            // The engine doesn't report descriptors
+            open_fds: None,
+        })
+    }
+
+    async fn exit_reason(&self, worker: &WorkerRef) -> Option<String> {
+        let id = worker
+            .handle
+            .clone()
+            .unwrap_or_else(|| worker_name(&worker.id));
+        let info = self.inspect(&id).await.ok()?;
+        (info["State"]["OOMKilled"] == true).then(|| OOM_KILLED.to_string())
+    }
+
+    // Running containers of ours, identified by their labels
+    async fn discover(&self) -> anyhow::Result<Vec<WorkerRef>> {
+        let config = self.config.get();
+        let filters = json!({
+            "label": [format!("{}=true", MANAGED_LABEL)],
+            "status": ["running"],
+        })
+        .to_string();
+        let response = self
+            .client
+            .get(self.url("/containers/json"))
+            .query(&[("filters", filters.as_str())])
+            .send()
+            .await?;
+        let containers: Vec<Value> = check(response, "list containers").await?.json().await?;
+
+        let mut found = Vec::new();
+        for container in containers {
+            let Some(id) = container["Id"].as_str() else {
+                continue;
+            };
+            // The token is only in the full container config
+            let info = self.inspect(id).await?;
+            let Some(worker_id) = info["Config"]["Labels"][WORKER_LABEL].as_str() else {
+                continue;
+            };
+            let token = info["Config"]["Env"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find_map(|e| e.as_str()?.strip_prefix("WORKER_TOKEN="))
+                .unwrap_or_default();
+            let port = match &config.container.network {
+                Some(_) => None,
+                None => published_port(&info, config.container.port).ok(),
+            };
+            found.push(WorkerRef {
+                id: worker_id.to_string(),
+                port,
+                token: token.to_string(),
+                handle: Some(id.to_string()),
+            });
+        }
+        Ok(found)
+    }
+
+    // Containers of ours that are no longer running, left behind by a previous run. Running
+    // ones may still back a live session.
+    async fn cleanup_orphans(&self) -> anyhow::Result<usize> {
+        let filters = json!({ "label": [format!("{}=true", MANAGED_LABEL)] }).to_string();
+        let response = self
+            .client
+            .get(self.url("/containers/json"))
+            .query(&[("all", "true"), ("filters", filters.as_str())])
+            .send()
+            .await?;
+        let containers: Vec<Value> = check(response, "list containers").await?.json().await?;
+
+        let mut removed = 0;
+        for container in containers {
+            if container["State"].as_str() == Some("running") {
+                continue;
+            }
+            let Some(id) = container["Id"].as_str() else {
+                continue;
+            };
+            self.remove(id).await?;
+            tracing::info!(container_id = id, "removed orphaned worker container");
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+fn engine_client(socket: &Path) -> anyhow::Result<Client> {
+    Ok(Client::builder().unix_socket(socket).build()?)
+}
+
+fn create_body(config: &ContainerConfig, request: &SpawnRequest) -> Value {
+    let port_key = format!("{}/tcp", config.port);
+    let mut host_config = json!({
+        "Memory": config.memory_bytes.unwrap_or(0),
+        "NanoCpus": config.cpus.map(|c| (c * 1e9) as i64).unwrap_or(0),
+        "PidsLimit": config.pids_limit,
+    });
+    match &config.network {
+        Some(network) => host_config["NetworkMode"] = json!(network),
+        // Empty HostPort lets the engine pick a free one
+        None => {
+            host_config["PortBindings"] =
+                json!({ &port_key: [{ "HostIp": "127.0.0.1", "HostPort": "" }] })
+        }
+    }
+    json!({
+        "Image": config.image,
+        "Env": [
+            format!("PORT={}", config.port),
+            format!("WORKER_TOKEN={}", request.token),
+        ],
+        "Labels": {
+            MANAGED_LABEL: "true",
+            WORKER_LABEL: request.worker_id,
+            USER_LABEL: request.user,
+        },
+        "ExposedPorts": { port_key: {} },
+        "HostConfig": host_config,
+    })
+}
+
+fn published_port(info: &Value, container_port: u16) -> anyhow::Result<u16> {
+    info["NetworkSettings"]["Ports"][format!("{}/tcp", container_port)][0]["HostPort"]
+        .as_str()
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| anyhow!("Container port {} was not published", container_port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BackendKind, Config};
+    use axum::extract::{Path as UrlPath, Query, State};
+    use axum::routing::{delete, get, post};
+    use axum::{Json, Router};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::UnixListener;
+
+    // Records what the backend asked the engine for
+    #[derive(Clone, Default)]
+    struct FakeEngine {
+        created: Arc<Mutex<Vec<(String, Value)>>>,
+        removed: Arc<Mutex<Vec<String>>>,
+    }
+
+    async fn create(
+        State(engine): State<FakeEngine>,
+        Query(query): Query<HashMap<String, String>>,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        let name = query.get("name").cloned().unwrap_or_default();
+        engine.created.lock().unwrap().push((name, body));
+        Json(json!({ "Id": "c0ffee", "Warnings": [] }))
+    }
+
+    async fn inspect(UrlPath(id): UrlPath<String>) -> Json<Value> {
+        assert_eq!(id, "c0ffee");
+        Json(json!({
+            "Id": id,
+            "NetworkSettings": { "Ports": { "3000/tcp": [{ "HostIp": "127.0.0.1", "HostPort": "49153" }] } }
+        }))
+    }
+
+    async fn list() -> Json<Value> {
+        Json(json!([
+            { "Id": "live", "State": "running" },
+            { "Id": "dead", "State": "exited" },
+        ]))
+    }
+
+    async fn remove(State(engine): State<FakeEngine>, UrlPath(id): UrlPath<String>) -> StatusCode {
+        engine.removed.lock().unwrap().push(id);
+        StatusCode::NO_CONTENT
+    }
+
+    async fn start_engine() -> (FakeEngine, ContainerBackend) {
+        let socket = std::env::temp_dir().join(format!("engine-{}.sock", uuid::Uuid::new_v4()));
+        let engine = FakeEngine::default();
+        let app = Router::new()
+            .route("/v1.41/containers/create", post(create))
+            .route(
+                "/v1.41/containers/{id}/start",
+                post(|| async { StatusCode::NO_CONTENT }),
+            )
+            .route("/v1.41/containers/{id}/json", get(inspect))
+            .route("/v1.41/containers/json", get(list))
+            .route("/v1.41/containers/{id}", delete(remove))
+            .with_state(engine.clone());
+        let listener = UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = Config::default();
+        config.worker.backend = BackendKind::Container;
+        config.container.socket = socket;
+        config.container.image = "steel-browser:test".to_string();
+        config.container.memory_bytes = Some(512 * 1024 * 1024);
+        config.container.cpus = Some(1.5);
+        let backend = ContainerBackend::new(ConfigHandle::new(config)).unwrap();
+        (engine, backend)
+    }
+
+    #[tokio::test]
+    async fn spawn_creates_labelled_container_with_limits() {
+        let (engine, backend) = start_engine().await;
+        let spawned = backend
+            .spawn(&SpawnRequest {
+                worker_id: "w1".to_string(),
+                token: "secret".to_string(),
+                user: "alice".to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(spawned.port, Some(49153));
+        assert_eq!(spawned.handle.as_deref(), Some("c0ffee"));
+        let created = engine.created.lock().unwrap();
+        let (name, body) = &created[0];
+        assert_eq!(name, "orchestrator-worker-w1");
+        assert_eq!(body["Image"], "steel-browser:test");
+        assert_eq!(body["Labels"][WORKER_LABEL], "w1");
+        assert_eq!(body["Labels"][MANAGED_LABEL], "true");
+        assert_eq!(body["HostConfig"]["Memory"], 512 * 1024 * 1024);
+        assert_eq!(body["HostConfig"]["NanoCpus"], 1_500_000_000i64);
+        assert!(
+            body["Env"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("WORKER_TOKEN=secret"))
+        );
+    }
+
+    #[tokio::test]
+    async fn spawn_removes_container_without_published_port() {
+        let (engine, backend) = start_engine().await;
+        // The engine only publishes port 3000
+        let mut config = (*backend.config.get()).clone();
+        config.container.port = 4000;
+        let backend = ContainerBackend::new(ConfigHandle::new(config)).unwrap();
+
+        let spawned = backend
+            .spawn(&SpawnRequest {
+                worker_id: "w1".to_string(),
+                token: "secret".to_string(),
+                user: "alice".to_string(),
+            })
+            .await;
+
+        assert!(spawned.is_err());
+        assert_eq!(*engine.removed.lock().unwrap(), vec!["c0ffee".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn stop_and_cleanup_remove_by_id() {
+        let (engine, backend) = start_engine().await;
+        let worker = WorkerRef {
+            id: "w1".to_string(),
+            handle: Some("c0ffee".to_string()),
+            ..Default::default()
+        };
+        backend.stop(&worker).await.unwrap();
+        assert_eq!(backend.cleanup_orphans().await.unwrap(), 1);
+
+        assert_eq!(*engine.removed.lock().unwrap(), vec!["c0ffee", "dead"]);
+    }
+}
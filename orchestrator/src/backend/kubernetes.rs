@@ -0,0 +1,600 @@
+use super::{
+    MANAGED_LABEL, OOM_KILLED, SpawnRequest, SpawnedWorker, USER_LABEL, WORKER_LABEL,
+    WorkerBackend, WorkerRef, check, worker_name,
+};
+use crate::config::{ConfigHandle, KubernetesConfig};
+use anyhow::{Context, anyhow, bail};
+use async_trait::async_trait;
+use reqwest::{Certificate, Client, RequestBuilder, StatusCode};
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Pause between readiness polls while a pod starts
+const READY_POLL: Duration = Duration::from_millis(500);
+
+// steel-browser as a pod, created through the Kubernetes API from a pod template. Requests
+// go to the pod IP, or to a per-pod Service when configured.
+pub struct KubernetesBackend {
+    config: ConfigHandle,
+    client: Client,
+}
+
+impl KubernetesBackend {
+    pub fn new(config: ConfigHandle) -> anyhow::Result<Self> {
+        let mut builder = Client::builder();
+        let ca_file = config.get().kubernetes.ca_file.clone();
+        if ca_file.exists() {
+            let pem = std::fs::read(&ca_file)
+                .with_context(|| format!("Error reading {}", ca_file.display()))?;
+            builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+        Ok(KubernetesBackend {
+            config,
+            client: builder.build()?,
+        })
+    }
+
+    fn url(&self, config: &KubernetesConfig, path: &str) -> String {
+        format!(
+            "{}/api/v1/namespaces/{}/{}",
+            config.api_url, config.namespace, path
+        )
+    }
+
+    // Service account tokens are rotated, so the file is read per request
+    fn authorized(&self, config: &KubernetesConfig, request: RequestBuilder) -> RequestBuilder {
+        match std::fs::read_to_string(&config.token_file) {
+            Ok(token) => request.bearer_auth(token.trim()),
+            Err(_) => request,
+        }
+    }
+
+    async fn get_pod(&self, config: &KubernetesConfig, name: &str) -> anyhow::Result<Value> {
+        let request = self.client.get(self.url(config, &format!("pods/{}", name)));
+        let response = self.authorized(config, request).send().await?;
+        Ok(check(response, "get pod").await?.json().await?)
+    }
+
+    async fn create(
+        &self,
+        config: &KubernetesConfig,
+        kind: &str,
+        body: &Value,
+    ) -> anyhow::Result<()> {
+        let request = self.client.post(self.url(config, kind)).json(body);
+        let response = self.authorized(config, request).send().await?;
+        check(response, &format!("create {}", kind)).await?;
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        config: &KubernetesConfig,
+        kind: &str,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let request = self
+            .client
+            .delete(self.url(config, &format!("{}/{}", kind, name)));
+        let response = self.authorized(config, request).send().await?;
+        // Already gone is what we wanted
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(response, &format!("delete {}", kind)).await?;
+        Ok(())
+    }
+
+    async fn list_pods(&self, config: &KubernetesConfig) -> anyhow::Result<Value> {
+        let selector = format!("{}=true", MANAGED_LABEL);
+        let request = self
+            .client
+            .get(self.url(config, "pods"))
+            .query(&[("labelSelector", selector.as_str())]);
+        let response = self.authorized(config, request).send().await?;
+        Ok(check(response, "list pods").await?.json().await?)
+    }
+
+    // Removes the pod and, when configured, its Service
+    async fn remove(&self, config: &KubernetesConfig, name: &str) -> anyhow::Result<()> {
+        if config.service {
+            self.delete(config, "services", name).await?;
+        }
+        self.delete(config, "pods", name).await
+    }
+
+    // Returns the ready pod
+    async fn wait_ready(&self, config: &KubernetesConfig, name: &str) -> anyhow::Result<Value> {
+        let deadline = Instant::now() + config.ready_timeout();
+        loop {
+            let pod = self.get_pod(config, name).await?;
+            if is_ready(&pod) {
+                return Ok(pod);
+            }
+            if let Some(phase @ ("Failed" | "Succeeded")) = pod["status"]["phase"].as_str() {
+                bail!("Pod {} ended before becoming ready: {}", name, phase);
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "Pod {} not ready after {}s",
+                    name,
+                    config.ready_timeout_secs
+                );
+            }
+            tokio::time::sleep(READY_POLL).await;
+        }
+    }
+}
+
+#[async_trait]
+impl WorkerBackend for KubernetesBackend {
+    fn name(&self) -> &'static str {
+        "kubernetes"
+    }
+
+    async fn spawn(&self, request: &SpawnRequest) -> anyhow::Result<SpawnedWorker> {
+        let config = self.config.get();
+        let config = &config.kubernetes;
+        let name = worker_name(&request.worker_id);
+        let template = match &config.pod_template {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path)
+                    .with_context(|| format!("Error reading {}", path.display()))?;
+                serde_json::from_str(&raw)
+                    .with_context(|| format!("Invalid pod template {}", path.display()))?
+            }
+            None => default_template(config),
+        };
+        let pod = pod_manifest(template, config, &name, request)?;
+
+        self.create(config, "pods", &pod).await?;
+        let ready = async {
+            if config.service {
+                self.create(
+                    config,
+                    "services",
+                    &service_manifest(config, &name, request),
+                )
+                .await?;
+            }
+            self.wait_ready(config, &name).await
+        };
+        let pod = match ready.await {
+            Ok(pod) => pod,
+            Err(e) => {
+                let _ = self.remove(config, &name).await;
+                return Err(e);
+            }
+        };
+        Ok(SpawnedWorker {
+            port: Some(config.port),
+            handle: Some(name),
+            node: pod["spec"]["nodeName"].as_str().map(str::to_string),
+        })
+    }
+
+    async fn endpoint(&self, worker: &WorkerRef) -> anyhow::Result<String> {
+        let config = self.config.get();
+        let config = &config.kubernetes;
+        let name = worker
+            .handle
+            .clone()
+            .unwrap_or_else(|| worker_name(&worker.id));
+        let port = worker.port.unwrap_or(config.port);
+        if config.service {
+            return Ok(format!("http://{}.{}.svc:{}", name, config.namespace, port));
+        }
+        let pod = self.get_pod(config, &name).await?;
+        let ip = pod["status"]["podIP"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Pod {} has no IP", name))?;
+        Ok(format!("http://{}:{}", ip, port))
+    }
+
+    async fn stop(&self, worker: &WorkerRef) -> anyhow::Result<()> {
+        let config = self.config.get();
+        let name = worker
+            .handle
+            .clone()
+            .unwrap_or_else(|| worker_name(&worker.id));
+        self.remove(&config.kubernetes, &name).await
+    }
+
+    // The kubelet records OOM kills in the container statuses
+    async fn exit_reason(&self, worker: &WorkerRef) -> Option<String> {
+        let config = self.config.get();
+        let name = worker
+            .handle
+            .clone()
+            .unwrap_or_else(|| worker_name(&worker.id));
+        let pod = self.get_pod(&config.kubernetes, &name).await.ok()?;
+        let oom_killed = pod["status"]["containerStatuses"]
+            .as_array()?
+            .iter()
+            .flat_map(|status| [&status["state"], &status["lastState"]])
+            .any(|state| state["terminated"]["reason"] == "OOMKilled");
+        oom_killed.then(|| OOM_KILLED.to_string())
+    }
+
+    // Running pods of ours, identified by their labels
+    async fn discover(&self) -> anyhow::Result<Vec<WorkerRef>> {
+        let config = self.config.get();
+        let config = &config.kubernetes;
+        let pods = self.list_pods(config).await?;
+
+        let mut found = Vec::new();
+        for pod in pods["items"].as_array().into_iter().flatten() {
+            if pod["status"]["phase"] != "Running" {
+                continue;
+            }
+            let (Some(name), Some(worker_id)) = (
+                pod["metadata"]["name"].as_str(),
+                pod["metadata"]["labels"][WORKER_LABEL].as_str(),
+            ) else {
+                continue;
+            };
+            let token = pod["spec"]["containers"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|c| c["env"].as_array().into_iter().flatten())
+                .find(|e| e["name"] == "WORKER_TOKEN")
+                .and_then(|e| e["value"].as_str())
+                .unwrap_or_default();
+            found.push(WorkerRef {
+                id: worker_id.to_string(),
+                port: Some(config.port),
+                token: token.to_string(),
+                handle: Some(name.to_string()),
+            });
+        }
+        Ok(found)
+    }
+
+    // Pods of ours that have finished, left behind by a previous run. Pending and running
+    // ones may still back a live session.
+    async fn cleanup_orphans(&self) -> anyhow::Result<usize> {
+        let config = self.config.get();
+        let config = &config.kubernetes;
+        let pods = self.list_pods(config).await?;
+
+        let mut removed = 0;
+        for pod in pods["items"].as_array().into_iter().flatten() {
+            if !matches!(
+                pod["status"]["phase"].as_str(),
+                Some("Failed" | "Succeeded")
+            ) {
+                continue;
+            }
+            let Some(name) = pod["metadata"]["name"].as_str() else {
+                continue;
+            };
+            self.remove(config, name).await?;
+            tracing::info!(pod = name, "removed orphaned worker pod");
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+fn default_template(config: &KubernetesConfig) -> Value {
+    json!({
+        "apiVersion": "v1",
+        "kind": "Pod",
+        "spec": {
+            // One pod per session, a crashed worker is replaced rather than restarted
+            "restartPolicy": "Never",
+            "containers": [{ "name": "worker", "image": config.image }],
+        },
+    })
+}
+
+// Fills the template in with the name, labels and worker settings of this spawn
+fn pod_manifest(
+    mut pod: Value,
+    config: &KubernetesConfig,
+    name: &str,
+    request: &SpawnRequest,
+) -> anyhow::Result<Value> {
+    if !pod["metadata"].is_object() {
+        pod["metadata"] = json!({});
+    }
+    let metadata = &mut pod["metadata"];
+    metadata["name"] = json!(name);
+    metadata["namespace"] = json!(config.namespace);
+    if !metadata["labels"].is_object() {
+        metadata["labels"] = json!({});
+    }
+    metadata["labels"][MANAGED_LABEL] = json!("true");
+    metadata["labels"][WORKER_LABEL] = json!(request.worker_id);
+    // User names aren't valid label values in general
+    if !metadata["annotations"].is_object() {
+        metadata["annotations"] = json!({});
+    }
+    metadata["annotations"][USER_LABEL] = json!(request.user);
+
+    let containers = pod["spec"]["containers"]
+        .as_array_mut()
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| anyhow!("Pod template has no containers"))?;
+    let index = containers
+        .iter()
+        .position(|c| c["name"] == "worker")
+        .unwrap_or(0);
+    let container = &mut containers[index];
+    if container["image"].as_str().is_none_or(str::is_empty) {
+        container["image"] = json!(config.image);
+    }
+    let mut env: Vec<Value> = container["env"].as_array().cloned().unwrap_or_default();
+    env.retain(|e| e["name"] != "PORT" && e["name"] != "WORKER_TOKEN");
+    env.push(json!({ "name": "PORT", "value": config.port.to_string() }));
+    env.push(json!({ "name": "WORKER_TOKEN", "value": request.token }));
+    container["env"] = json!(env);
+    container["ports"] = json!([{ "containerPort": config.port, "name": "http" }]);
+    if container["readinessProbe"].is_null() {
+        container["readinessProbe"] = json!({
+            "httpGet": {
+                "path": "/health",
+                "port": config.port,
+                "httpHeaders": [{ "name": "Authorization", "value": format!("Bearer {}", request.token) }],
+            },
+            "periodSeconds": 1,
+        });
+    }
+    Ok(pod)
+}
+
+fn service_manifest(config: &KubernetesConfig, name: &str, request: &SpawnRequest) -> Value {
+    json!({
+        "apiVersion": "v1",
+        "kind": "Service",
+        "metadata": {
+            "name": name,
+            "namespace": config.namespace,
+            "labels": { MANAGED_LABEL: "true", WORKER_LABEL: request.worker_id },
+        },
+        "spec": {
+            "selector": { WORKER_LABEL: request.worker_id },
+            "ports": [{ "port": config.port, "targetPort": config.port }],
+        },
+    })
+}
+
+fn is_ready(pod: &Value) -> bool {
+    pod["status"]["conditions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|c| c["type"] == "Ready" && c["status"] == "True")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BackendKind, Config};
+    use axum::extract::{Path, State};
+    use axum::http::Uri;
+    use axum::routing::{delete, get, post};
+    use axum::{Json, Router};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    // Stand-in API server keeping pods in memory. Pods turn Ready once read more than
+    // `pending_reads` times.
+    #[derive(Clone)]
+    struct FakeApiServer {
+        pending_reads: usize,
+        pods: Arc<Mutex<HashMap<String, Value>>>,
+        reads: Arc<Mutex<HashMap<String, usize>>>,
+        services: Arc<Mutex<Vec<String>>>,
+        deleted: Arc<Mutex<Vec<String>>>,
+    }
+
+    async fn create_pod(State(api): State<FakeApiServer>, Json(pod): Json<Value>) -> Json<Value> {
+        let name = pod["metadata"]["name"].as_str().unwrap().to_string();
+        api.pods.lock().unwrap().insert(name, pod.clone());
+        Json(pod)
+    }
+
+    async fn get_pod(
+        State(api): State<FakeApiServer>,
+        Path((_, name)): Path<(String, String)>,
+    ) -> Result<Json<Value>, StatusCode> {
+        let mut pod = api
+            .pods
+            .lock()
+            .unwrap()
+            .get(&name)
+            .cloned()
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let mut reads = api.reads.lock().unwrap();
+        let count = reads.entry(name).or_default();
+        *count += 1;
+        if *count > api.pending_reads {
+            pod["spec"]["nodeName"] = json!("node-a");
+            pod["status"] = json!({ "phase": "Running", "podIP": "10.0.0.7", "conditions": [{ "type": "Ready", "status": "True" }] });
+        } else {
+            pod["status"] = json!({ "phase": "Pending" });
+        }
+        Ok(Json(pod))
+    }
+
+    async fn list_pods() -> Json<Value> {
+        Json(json!({ "items": [
+            { "metadata": { "name": "orchestrator-worker-live" }, "status": { "phase": "Running" } },
+            { "metadata": { "name": "orchestrator-worker-done" }, "status": { "phase": "Failed" } },
+        ] }))
+    }
+
+    async fn delete_object(State(api): State<FakeApiServer>, uri: Uri) -> StatusCode {
+        // Record "<kind>/<name>"
+        let path = uri.path().splitn(6, '/').last().unwrap().to_string();
+        api.deleted.lock().unwrap().push(path);
+        StatusCode::OK
+    }
+
+    async fn create_service(
+        State(api): State<FakeApiServer>,
+        Json(service): Json<Value>,
+    ) -> Json<Value> {
+        let name = service["metadata"]["name"].as_str().unwrap().to_string();
+        api.services.lock().unwrap().push(name);
+        Json(service)
+    }
+
+    async fn start_api(
+        pending_reads: usize,
+        configure: impl FnOnce(&mut Config),
+    ) -> (FakeApiServer, KubernetesBackend) {
+        let api = FakeApiServer {
+            pending_reads,
+            pods: Default::default(),
+            reads: Default::default(),
+            services: Default::default(),
+            deleted: Default::default(),
+        };
+        let app = Router::new()
+            .route(
+                "/api/v1/namespaces/{ns}/pods",
+                post(create_pod).get(list_pods),
+            )
+            .route(
+                "/api/v1/namespaces/{ns}/pods/{name}",
+                get(get_pod).delete(delete_object),
+            )
+            .route("/api/v1/namespaces/{ns}/services", post(create_service))
+            .route(
+                "/api/v1/namespaces/{ns}/services/{name}",
+                delete(delete_object),
+            )
+            .with_state(api.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = Config::default();
+        config.worker.backend = BackendKind::Kubernetes;
+        config.kubernetes.api_url = format!("http://{}", addr);
+        config.kubernetes.namespace = "browsers".to_string();
+        config.kubernetes.token_file = "/nonexistent".into();
+        config.kubernetes.ca_file = "/nonexistent".into();
+        configure(&mut config);
+        let backend = KubernetesBackend::new(ConfigHandle::new(config)).unwrap();
+        (api, backend)
+    }
+
+    fn spawn_request() -> SpawnRequest {
+        SpawnRequest {
+            worker_id: "w1".to_string(),
+            token: "secret".to_string(),
+            user: "alice@example.com".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn spawn_waits_for_ready_and_routes_to_pod_ip() {
+        let template = std::env::temp_dir().join(format!("pod-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &template,
+            json!({
+                "metadata": { "labels": { "team": "browsers" } },
+                "spec": {
+                    "nodeSelector": { "pool": "browsers" },
+                    "containers": [
+                        { "name": "proxy", "image": "envoy" },
+                        { "name": "worker", "image": "steel-browser:pinned" },
+                    ],
+                },
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let (api, backend) = start_api(1, |c| c.kubernetes.pod_template = Some(template)).await;
+
+        let spawned = backend.spawn(&spawn_request()).await.unwrap();
+        assert_eq!(spawned.handle.as_deref(), Some("orchestrator-worker-w1"));
+        assert_eq!(spawned.node.as_deref(), Some("node-a"));
+
+        let pod = api.pods.lock().unwrap()["orchestrator-worker-w1"].clone();
+        assert_eq!(pod["metadata"]["labels"]["team"], "browsers");
+        assert_eq!(pod["metadata"]["labels"][WORKER_LABEL], "w1");
+        assert_eq!(
+            pod["metadata"]["annotations"][USER_LABEL],
+            "alice@example.com"
+        );
+        assert_eq!(pod["spec"]["nodeSelector"]["pool"], "browsers");
+        let worker = &pod["spec"]["containers"][1];
+        assert_eq!(worker["image"], "steel-browser:pinned");
+        assert!(
+            worker["env"]
+                .as_array()
+                .unwrap()
+                .contains(&json!({ "name": "WORKER_TOKEN", "value": "secret" }))
+        );
+        assert!(pod["spec"]["containers"][0]["env"].is_null());
+
+        let worker = WorkerRef {
+            id: "w1".to_string(),
+            port: spawned.port,
+            handle: spawned.handle,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.endpoint(&worker).await.unwrap(),
+            "http://10.0.0.7:3000"
+        );
+    }
+
+    #[tokio::test]
+    async fn service_routing_and_stop() {
+        let (api, backend) = start_api(1, |c| c.kubernetes.service = true).await;
+        let spawned = backend.spawn(&spawn_request()).await.unwrap();
+        assert_eq!(
+            *api.services.lock().unwrap(),
+            vec!["orchestrator-worker-w1"]
+        );
+
+        let worker = WorkerRef {
+            id: "w1".to_string(),
+            port: spawned.port,
+            handle: spawned.handle,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.endpoint(&worker).await.unwrap(),
+            "http://orchestrator-worker-w1.browsers.svc:3000"
+        );
+        backend.stop(&worker).await.unwrap();
+        assert_eq!(
+            *api.deleted.lock().unwrap(),
+            vec![
+                "services/orchestrator-worker-w1",
+                "pods/orchestrator-worker-w1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn spawn_gives_up_and_cleans_up_after_timeout() {
+        let (api, backend) = start_api(usize::MAX, |c| c.kubernetes.ready_timeout_secs = 1).await;
+
+        let err = backend.spawn(&spawn_request()).await.unwrap_err();
+        assert!(err.to_string().contains("not ready"), "{err}");
+        assert_eq!(
+            *api.deleted.lock().unwrap(),
+            vec!["pods/orchestrator-worker-w1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_finished_pods_by_label() {
+        let (api, backend) = start_api(1, |_| {}).await;
+        assert_eq!(backend.cleanup_orphans().await.unwrap(), 1);
+        assert_eq!(
+            *api.deleted.lock().unwrap(),
+            vec!["pods/orchestrator-worker-done"]
+        );
+    }
+}
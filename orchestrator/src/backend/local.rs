@@ -0,0 +1,290 @@
+use super::usage::{self, parent_pid};
+use super::{
+    OOM_KILLED, ResourceUsage, SpawnRequest, SpawnedWorker, WORKER_ID_ENV, WorkerBackend,
+    WorkerRef, cgroup,
+};
+use crate::config::{ConfigHandle, LimitsConfig};
+use crate::logs::WORKER_LOGS;
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+
+// steel-browser as a child process of the orchestrator, listening on a free local port
+pub struct LocalProcessBackend {
+    config: ConfigHandle,
+}
+
+impl LocalProcessBackend {
+    pub fn new(config: ConfigHandle) -> Self {
+        LocalProcessBackend { config }
+    }
+}
+
+// What the handle of a worker running as a local process records, written "<pid>" or
+// "<pid>:<cgroup>". The cgroup is kept rather than derived from limits.cgroup_root, which a
+// reload may unset.
+pub struct ProcessHandle {
+    pub pid: Option<i32>,
+    pub cgroup: Option<PathBuf>,
+}
+
+impl ProcessHandle {
+    pub fn encode(pid: i32, cgroup: Option<&PathBuf>) -> String {
+        match cgroup {
+            Some(dir) => format!("{}:{}", pid, dir.display()),
+            None => pid.to_string(),
+        }
+    }
+
+    // Handles recorded before cgroups were kept only hold the pid, their cgroup is looked
+    // up under the configured root
+    pub fn decode(worker: &WorkerRef, limits: &LimitsConfig) -> Self {
+        let Some(handle) = worker.handle.as_deref() else {
+            return ProcessHandle {
+                pid: None,
+                cgroup: cgroup::cgroup_dir(limits, &worker.id),
+            };
+        };
+        match handle.split_once(':') {
+            Some((pid, dir)) => ProcessHandle {
+                pid: pid.parse().ok(),
+                cgroup: Some(PathBuf::from(dir)),
+            },
+            None => ProcessHandle {
+                pid: handle.parse().ok(),
+                cgroup: cgroup::cgroup_dir(limits, &worker.id),
+            },
+        }
+    }
+
+    pub async fn remove_cgroup(&self) -> anyhow::Result<()> {
+        match &self.cgroup {
+            Some(dir) => cgroup::remove(dir).await,
+            None => Ok(()),
+        }
+    }
+
+    pub fn oom_killed(&self) -> Option<String> {
+        self.cgroup
+            .as_deref()
+            .is_some_and(cgroup::oom_killed)
+            .then(|| OOM_KILLED.to_string())
+    }
+}
+
+#[async_trait]
+impl WorkerBackend for LocalProcessBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn spawn(&self, request: &SpawnRequest) -> anyhow::Result<SpawnedWorker> {
+        let config = self.config.get();
+        let port = get_port(config.worker.port_min, config.worker.port_max);
+        let mut command = Command::new(&config.worker.binary);
+        command
+            .env("PORT", port.unwrap_or_default().to_string())
+            .env("WORKER_TOKEN", &request.token)
+            .env(WORKER_ID_ENV, &request.worker_id)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let cgroup = cgroup::apply(&mut command, &config.limits, &request.worker_id)?;
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Error starting {}", config.worker.binary))?;
+        WORKER_LOGS.capture(&request.worker_id, &mut child);
+        Ok(SpawnedWorker {
+            port,
+            handle: child
+                .id()
+                .map(|pid| ProcessHandle::encode(pid as i32, cgroup.as_ref())),
+            node: None,
+        })
+    }
+
+    async fn endpoint(&self, worker: &WorkerRef) -> anyhow::Result<String> {
+        let port = worker
+            .port
+            .ok_or_else(|| anyhow!("Error fetching worker port"))?;
+        Ok(format!("http://localhost:{}", port))
+    }
+
+    async fn stop(&self, worker: &WorkerRef) -> anyhow::Result<()> {
+        let handle = ProcessHandle::decode(worker, &self.config.get().limits);
+        // Workers recorded before pids were kept can't be signalled
+        if let Some(pid) = handle.pid
+            && is_worker(pid, &worker.id)
+        {
+            terminate(pid)?;
+        }
+        handle.remove_cgroup().await
+    }
+
+    async fn usage(&self, worker: &WorkerRef) -> Option<ResourceUsage> {
+        let handle = ProcessHandle::decode(worker, &self.config.get().limits);
+        // Once the worker exited its pid may be some other process's
+        if handle.pid.is_some_and(|pid| !is_worker(pid, &worker.id)) {
+            return None;
+        }
+        usage::sample(handle.cgroup.as_deref(), handle.pid)
+    }
+
+    async fn exit_reason(&self, worker: &WorkerRef) -> Option<String> {
+        ProcessHandle::decode(worker, &self.config.get().limits).oom_killed()
+    }
+
+    // Processes carrying the worker id marker. A worker's own children inherit it, only the
+    // topmost marked process counts.
+    async fn discover(&self) -> anyhow::Result<Vec<WorkerRef>> {
+        let mut marked = HashMap::new();
+        for entry in std::fs::read_dir("/proc")? {
+            let Ok(pid) = entry?.file_name().to_string_lossy().parse::<i32>() else {
+                continue;
+            };
+            // Unreadable for other users' processes, empty for zombies
+            let Ok(environ) = std::fs::read(format!("/proc/{}/environ", pid)) else {
+                continue;
+            };
+            let vars: HashMap<String, String> = environ
+                .split(|b| *b == 0)
+                .filter_map(|pair| {
+                    let pair = String::from_utf8_lossy(pair);
+                    let (key, value) = pair.split_once('=')?;
+                    Some((key.to_string(), value.to_string()))
+                })
+                .collect();
+            let Some(id) = vars.get(WORKER_ID_ENV) else {
+                continue;
+            };
+            let worker = WorkerRef {
+                id: id.clone(),
+                port: vars.get("PORT").and_then(|p| p.parse().ok()),
+                token: vars.get("WORKER_TOKEN").cloned().unwrap_or_default(),
+                handle: Some(ProcessHandle::encode(
+                    pid,
+                    cgroup::of_process(pid, id).as_ref(),
+                )),
+            };
+            marked.insert(pid, (parent_pid(pid), worker));
+        }
+        Ok(marked
+            .values()
+            .filter(|(ppid, _)| !ppid.is_some_and(|p| marked.contains_key(&p)))
+            .map(|(_, worker)| worker.clone())
+            .collect())
+    }
+}
+
+// SIGTERM, a process that already exited is not an error
+pub fn terminate(pid: i32) -> anyhow::Result<()> {
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ESRCH) {
+            return Err(err).with_context(|| format!("Failed to stop worker pid {}", pid));
+        }
+    }
+    Ok(())
+}
+
+// Whether `pid` is still the process started for `worker_id`. The kernel reuses the pids of
+// exited workers, a process that got one since must not be signalled.
+pub fn is_worker(pid: i32, worker_id: &str) -> bool {
+    let marker = format!("{}={}", WORKER_ID_ENV, worker_id);
+    std::fs::read(format!("/proc/{}/environ", pid)).is_ok_and(|environ| {
+        environ
+            .split(|b| *b == 0)
+            .any(|var| var == marker.as_bytes())
+    })
+}
+
+// Affected by TOCTOU, fix for improvement
+pub fn get_port(min_port: u16, max_port: u16) -> Option<u16> {
+    (min_port..max_port).find(|&p| TcpListener::bind(("0.0.0.0", p)).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worker(handle: Option<&str>) -> WorkerRef {
+        WorkerRef {
+            id: "w1".to_string(),
+            port: None,
+            token: String::new(),
+            handle: handle.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_the_marked_process_is_the_worker() {
+        let mut child = Command::new("sleep")
+            .arg("5")
+            .env(WORKER_ID_ENV, "w1")
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let pid = child.id().unwrap() as i32;
+        assert!(is_worker(pid, "w1"));
+        assert!(!is_worker(pid, "w2"));
+
+        child.kill().await.unwrap();
+        child.wait().await.unwrap();
+        assert!(!is_worker(pid, "w1"));
+    }
+
+    #[tokio::test]
+    async fn usage_is_only_sampled_from_the_worker() {
+        let backend = LocalProcessBackend::new(ConfigHandle::new(Default::default()));
+        let mut child = Command::new("sleep")
+            .arg("5")
+            .env(WORKER_ID_ENV, "w1")
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let handle = child.id().unwrap().to_string();
+        assert!(backend.usage(&worker(Some(&handle))).await.is_some());
+        // The same pid recorded for another worker, as after a reuse
+        let other = WorkerRef {
+            id: "w2".to_string(),
+            ..worker(Some(&handle))
+        };
+        assert!(backend.usage(&other).await.is_none());
+
+        child.kill().await.unwrap();
+        child.wait().await.unwrap();
+    }
+
+    #[test]
+    fn handles_keep_the_cgroup_across_config_changes() {
+        let cgroup = PathBuf::from("/sys/fs/cgroup/orchestrator/worker-w1");
+        let handle = ProcessHandle::encode(42, Some(&cgroup));
+        // A reload dropped the limits since the worker started
+        let decoded = ProcessHandle::decode(&worker(Some(&handle)), &LimitsConfig::default());
+        assert_eq!(decoded.pid, Some(42));
+        assert_eq!(decoded.cgroup, Some(cgroup));
+
+        let decoded = ProcessHandle::decode(&worker(Some("42")), &LimitsConfig::default());
+        assert_eq!(decoded.pid, Some(42));
+        assert_eq!(decoded.cgroup, None);
+    }
+
+    #[test]
+    fn pid_only_handles_fall_back_to_the_configured_root() {
+        let limits = LimitsConfig {
+            cgroup_root: Some(PathBuf::from("/sys/fs/cgroup/orchestrator")),
+            pids_limit: Some(64),
+            ..Default::default()
+        };
+        let decoded = ProcessHandle::decode(&worker(Some("42")), &limits);
+        assert_eq!(
+            decoded.cgroup,
+            Some(PathBuf::from("/sys/fs/cgroup/orchestrator/worker-w1"))
+        );
+    }
+}
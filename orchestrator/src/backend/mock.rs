@@ -0,0 +1,345 @@
+use super::{ResourceUsage, SpawnRequest, SpawnedWorker, WorkerBackend, WorkerRef};
+use crate::auth::constant_time_eq;
+use crate::config::{ConfigHandle, MockConfig};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use axum::extract::{Path, Request, State};
+use axum::http::{StatusCode, header::AUTHORIZATION};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+// Fake steel-browser workers served from in-process Axum servers, one per worker, so the
+// pool can run without the real binary. Faults come from the `mock` config section as it
+// is when a worker spawns.
+pub struct MockBackend {
+    config: ConfigHandle,
+    workers: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+#[derive(Clone)]
+struct MockWorker {
+    token: String,
+    faults: MockConfig,
+    sessions: Arc<Mutex<Vec<Value>>>,
+}
+
+#[derive(Deserialize)]
+struct CreateSession {
+    // The id the pool assigned, a fresh one when unset
+    #[serde(rename = "sessionId")]
+    session_id: Option<String>,
+    user: String,
+}
+
+impl MockBackend {
+    pub fn new(config: ConfigHandle) -> Self {
+        MockBackend {
+            config,
+            workers: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl WorkerBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn spawn(&self, request: &SpawnRequest) -> anyhow::Result<SpawnedWorker> {
+        let faults = self.config.get().mock.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let app = mock_router(MockWorker {
+            token: request.token.clone(),
+            faults: faults.clone(),
+            sessions: Default::default(),
+        });
+
+        let server = tokio::spawn(async move {
+            // Connections queue in the backlog until the worker "finished booting"
+            tokio::time::sleep(Duration::from_millis(faults.slow_start_ms)).await;
+            let serve = axum::serve(listener, app);
+            match faults.crash_after_ms {
+                Some(ms) => {
+                    tokio::select! {
+                        _ = serve => {}
+                        _ = tokio::time::sleep(Duration::from_millis(ms)) => {}
+                    }
+                }
+                None => {
+                    let _ = serve.await;
+                }
+            }
+        });
+        self.workers
+            .lock()
+            .unwrap()
+            .insert(request.worker_id.clone(), server);
+        Ok(SpawnedWorker {
+            port: Some(port),
+            handle: None,
+            node: None,
+        })
+    }
+
+    async fn endpoint(&self, worker: &WorkerRef) -> anyhow::Result<String> {
+        let port = worker
+            .port
+            .ok_or_else(|| anyhow!("Error fetching worker port"))?;
+        Ok(format!("http://127.0.0.1:{}", port))
+    }
+
+    async fn stop(&self, worker: &WorkerRef) -> anyhow::Result<()> {
+        if let Some(server) = self.workers.lock().unwrap().remove(&worker.id) {
+            server.abort();
+        }
+        Ok(())
+    }
+
+    async fn usage(&self, _worker: &WorkerRef) -> Option<ResourceUsage> {
+        let rss_bytes = self.config.get().mock.rss_bytes?;
+        Some(ResourceUsage {
+            rss_bytes,
+            ..Default::default()
+        })
+    }
+}
+
+fn mock_router(worker: MockWorker) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/status", get(status))
+        .route("/sessions", post(create_session))
+        .route(
+            "/sessions/{session_id}",
+            get(get_session).delete(delete_session),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            worker.clone(),
+            check_request,
+        ))
+        .with_state(worker)
+}
+
+// Token check like steel-browser's, then the server_errors fault
+async fn check_request(State(worker): State<MockWorker>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !worker.token.is_empty() && !constant_time_eq(presented.as_bytes(), worker.token.as_bytes())
+    {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if worker.faults.server_errors {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "injected failure").into_response();
+    }
+    next.run(request).await
+}
+
+fn respond(worker: &MockWorker, body: Value) -> Response {
+    if worker.faults.malformed_json {
+        let raw = body.to_string();
+        return raw[..raw.len() / 2].to_string().into_response();
+    }
+    Json(body).into_response()
+}
+
+async fn health() -> &'static str {
+    "ok"
+}
+
+// Carries the fields of both the pool's status check and its session listing
+async fn status(State(worker): State<MockWorker>) -> Response {
+    let session = worker.sessions.lock().unwrap().first().cloned();
+    let mut body =
+        session.unwrap_or_else(|| json!({ "id": "", "created_at": 0, "data": { "user": "" } }));
+    body["session_id"] = body["id"].clone();
+    body["available"] = json!(true);
+    respond(&worker, body)
+}
+
+async fn create_session(
+    State(worker): State<MockWorker>,
+    Json(request): Json<CreateSession>,
+) -> Response {
+    let session = json!({
+        "id": request.session_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        "created_at": SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        "data": { "user": request.user },
+    });
+    worker.sessions.lock().unwrap().push(session.clone());
+    respond(&worker, session)
+}
+
+async fn get_session(State(worker): State<MockWorker>, Path(session_id): Path<String>) -> Response {
+    let session = worker
+        .sessions
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|s| s["id"] == session_id)
+        .cloned();
+    match session {
+        Some(session) => respond(&worker, session),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn delete_session(
+    State(worker): State<MockWorker>,
+    Path(session_id): Path<String>,
+) -> StatusCode {
+    let mut sessions = worker.sessions.lock().unwrap();
+    let before = sessions.len();
+    sessions.retain(|s| s["id"] != session_id);
+    if sessions.len() < before {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{CreateSessionResponse, SessionStatusResponse};
+    use crate::backend::worker_client;
+    use crate::config::Config;
+    use reqwest::Client;
+
+    fn backend(faults: MockConfig) -> MockBackend {
+        MockBackend::new(ConfigHandle::new(Config {
+            mock: faults,
+            ..Default::default()
+        }))
+    }
+
+    async fn spawn(backend: &MockBackend) -> (WorkerRef, String, Client) {
+        let request = SpawnRequest {
+            worker_id: uuid::Uuid::new_v4().to_string(),
+            token: "secret".to_string(),
+            user: "alice".to_string(),
+        };
+        let spawned = backend.spawn(&request).await.unwrap();
+        let worker = WorkerRef {
+            id: request.worker_id,
+            port: spawned.port,
+            token: request.token,
+            handle: spawned.handle,
+        };
+        let base = backend.endpoint(&worker).await.unwrap();
+        let client = worker_client(&worker.token, &tracing::Span::none()).unwrap();
+        (worker, base, client)
+    }
+
+    #[tokio::test]
+    async fn serves_the_session_api_the_pool_parses() {
+        let backend = backend(MockConfig::default());
+        let (worker, base, client) = spawn(&backend).await;
+        assert!(backend.probe(&worker).await);
+
+        // Under the id the pool assigned
+        let id = uuid::Uuid::new_v4().to_string();
+        let created: CreateSessionResponse = client
+            .post(format!("{}/sessions", base))
+            .json(&json!({ "sessionId": id, "user": "alice" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(serde_json::to_value(&created).unwrap()["id"], id.as_str());
+
+        let status = client.get(format!("{}/status", base)).send().await.unwrap();
+        let status: Value = status.json().await.unwrap();
+        serde_json::from_value::<SessionStatusResponse>(status.clone()).unwrap();
+        serde_json::from_value::<CreateSessionResponse>(status).unwrap();
+
+        let session = client
+            .get(format!("{}/sessions/{}", base, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(session.status(), StatusCode::OK);
+        let deleted = client
+            .delete(format!("{}/sessions/{}", base, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+
+        backend.stop(&worker).await.unwrap();
+        assert!(!backend.probe(&worker).await);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_the_worker_token() {
+        let backend = backend(MockConfig::default());
+        let (_, base, _) = spawn(&backend).await;
+        let response = reqwest::get(format!("{}/health", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn slow_start_then_crash() {
+        let backend = backend(MockConfig {
+            slow_start_ms: 300,
+            crash_after_ms: Some(300),
+            ..Default::default()
+        });
+        let (worker, base, _) = spawn(&backend).await;
+        let client = Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        assert!(client.get(format!("{}/health", base)).send().await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(backend.probe(&worker).await);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!backend.probe(&worker).await);
+    }
+
+    #[tokio::test]
+    async fn server_errors_and_malformed_json() {
+        let backend_500 = backend(MockConfig {
+            server_errors: true,
+            ..Default::default()
+        });
+        let (worker, base, client) = spawn(&backend_500).await;
+        let response = client.get(format!("{}/status", base)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!backend_500.probe(&worker).await);
+
+        let backend_garbled = backend(MockConfig {
+            malformed_json: true,
+            ..Default::default()
+        });
+        let (_, base, client) = spawn(&backend_garbled).await;
+        let body = client
+            .post(format!("{}/sessions", base))
+            .json(&json!({ "user": "alice" }))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(serde_json::from_str::<CreateSessionResponse>(&body).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+pub mod cgroup;
+pub mod chromium;
+pub mod container;
+pub mod kubernetes;
+pub mod local;
+pub mod mock;
+pub mod usage;
+
+use crate::config::{BackendKind, ConfigHandle};
+use crate::telemetry;
+use anyhow::bail;
+use async_trait::async_trait;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use reqwest::{Client, Response};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+pub use chromium::ChromiumBackend;
+pub use container::ContainerBackend;
+pub use kubernetes::KubernetesBackend;
+pub use local::LocalProcessBackend;
+pub use mock::MockBackend;
+
+// Every container or pod we create carries these, orphans are found by them
+pub const MANAGED_LABEL: &str = "browser-orchestrator.managed";
+pub const WORKER_LABEL: &str = "browser-orchestrator.worker-id";
+pub const USER_LABEL: &str = "browser-orchestrator.user";
+// Set in every worker's environment, finds our processes after a restart
+pub const WORKER_ID_ENV: &str = "ORCHESTRATOR_WORKER_ID";
+// Exit reason of workers the kernel or runtime killed for exceeding their memory limit
+pub const OOM_KILLED: &str = "oom_killed";
+
+// What the pool persists about a worker, enough for its backend to find it again after a
+// restart
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WorkerRef {
+    pub id: String,
+    pub port: Option<u16>,
+    pub token: String,
+    // Backend specific: pid, container id, pod name
+    pub handle: Option<String>,
+}
+
+pub struct SpawnRequest {
+    pub worker_id: String,
+    // Bearer token the worker must require on its API
+    pub token: String,
+    pub user: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SpawnedWorker {
+    pub port: Option<u16>,
+    pub handle: Option<String>,
+    // Node the worker landed on, for backends that spread workers over several
+    #[serde(default)]
+    pub node: Option<String>,
+}
+
+// Where and how workers run. The pool only talks to workers through the steel-browser HTTP
+// API found at `endpoint`, everything else about a worker's lifecycle goes through here.
+#[async_trait]
+pub trait WorkerBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Starts a worker. It may still be booting when this returns.
+    async fn spawn(&self, request: &SpawnRequest) -> anyhow::Result<SpawnedWorker>;
+
+    // Base URL serving the steel-browser API, without a trailing slash
+    async fn endpoint(&self, worker: &WorkerRef) -> anyhow::Result<String>;
+
+    // Whether the worker is alive and answering, any error counts as unhealthy
+    async fn probe(&self, worker: &WorkerRef) -> bool {
+        match self.endpoint(worker).await {
+            Ok(base) => http_probe(&base, &worker.token).await,
+            Err(_) => false,
+        }
+    }
+
+    // Tears the worker down, stopping an already gone worker is not an error
+    async fn stop(&self, worker: &WorkerRef) -> anyhow::Result<()>;
+
+    // Current resource use, None when the backend can't measure it
+    async fn usage(&self, _worker: &WorkerRef) -> Option<ResourceUsage> {
+        None
+    }
+
+    // Why a worker that stopped answering died, when the backend can tell. Asked before
+    // `stop` removes the evidence.
+    async fn exit_reason(&self, _worker: &WorkerRef) -> Option<String> {
+        None
+    }
+
+    // Removes workers a previous run left behind, returns how many. Called once at startup.
+    async fn cleanup_orphans(&self) -> anyhow::Result<usize> {
+        Ok(0)
+    }
+
+    // Running workers this backend can find on its own, whether or not the pool knows them.
+    // Reconciliation compares them against the pool.
+    async fn discover(&self) -> anyhow::Result<Vec<WorkerRef>> {
+        Ok(Vec::new())
+    }
+}
+
+// One sample of a worker's resource use, summed over all of its processes
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct ResourceUsage {
+    pub rss_bytes: u64,
+    // Total since the worker started
+    pub cpu_seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_fds: Option<u64>,
+}
+
+pub fn from_config(config: &ConfigHandle) -> anyhow::Result<Arc<dyn WorkerBackend>> {
+    Ok(match config.get().worker.backend {
+        BackendKind::Local => Arc::new(LocalProcessBackend::new(config.clone())),
+        BackendKind::Container => Arc::new(ContainerBackend::new(config.clone())?),
+        BackendKind::Kubernetes => Arc::new(KubernetesBackend::new(config.clone())?),
+        BackendKind::Chromium => Arc::new(ChromiumBackend::new(config.clone())?),
+        BackendKind::Mock => Arc::new(MockBackend::new(config.clone())),
+    })
+}
+
+// Container and pod name, also what workers on a shared network are reached by
+pub fn worker_name(worker_id: &str) -> String {
+    format!("orchestrator-worker-{}", worker_id)
+}
+
+// Client for the steel-browser API, carrying the worker's token and the current trace context
+pub fn worker_client(token: &str, span: &tracing::Span) -> anyhow::Result<Client> {
+    let mut headers = HeaderMap::new();
+    telemetry::inject_context(span, &mut headers);
+    if !token.is_empty() {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token))?,
+        );
+    }
+    Ok(Client::builder().default_headers(headers).build()?)
+}
+
+// Turns an unsuccessful engine or API server response into an error carrying its body
+pub async fn check(response: Response, op: &str) -> anyhow::Result<Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    bail!("Failed to {}: {} {}", op, status, body)
+}
+
+// steel-browser answers "ok" on /health once it's up
+pub async fn http_probe(base: &str, token: &str) -> bool {
+    let Ok(client) = worker_client(token, &tracing::Span::current()) else {
+        return false;
+    };
+    match client.get(format!("{}/health", base)).send().await {
+        Ok(response) => response.text().await.is_ok_and(|body| body == "ok"),
+        Err(_) => false,
+    }
+}
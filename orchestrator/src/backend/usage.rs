@@ -0,0 +1,130 @@
+use super::ResourceUsage;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Samples a local worker: from its cgroup when it has one, otherwise from the process tree
+// under `pid`, since browsers fork renderers and helpers
+pub fn sample(cgroup: Option<&Path>, pid: Option<i32>) -> Option<ResourceUsage> {
+    if let Some(dir) = cgroup
+        && dir.exists()
+    {
+        return sample_cgroup(dir);
+    }
+    let pids = descendants(pid?);
+    if pids.is_empty() {
+        return None;
+    }
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64;
+    let mut usage = ResourceUsage {
+        open_fds: Some(0),
+        ..Default::default()
+    };
+    for pid in pids {
+        let resident = std::fs::read_to_string(format!("/proc/{}/statm", pid))
+            .ok()
+            .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<u64>().ok())
+            .unwrap_or_default();
+        usage.rss_bytes += resident * page_size;
+        // utime and stime, fields 14 and 15
+        if let Some(fields) = stat_fields(pid) {
+            let cpu: u64 = fields[11..13]
+                .iter()
+                .filter_map(|f| f.parse::<u64>().ok())
+                .sum();
+            usage.cpu_seconds += cpu as f64 / ticks;
+        }
+        usage.open_fds = usage.open_fds.map(|n| n + count_fds(pid));
+    }
+    Some(usage)
+}
+
+fn sample_cgroup(dir: &Path) -> Option<ResourceUsage> {
+    let read = |file: &str| std::fs::read_to_string(dir.join(file)).ok();
+    let rss_bytes = read("memory.current")?.trim().parse().ok()?;
+    let cpu_usec: u64 = read("cpu.stat")
+        .and_then(|stat| {
+            stat.lines()
+                .find_map(|line| line.strip_prefix("usage_usec "))
+                .and_then(|n| n.trim().parse().ok())
+        })
+        .unwrap_or_default();
+    let open_fds = read("cgroup.procs")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|pid| pid.parse::<i32>().ok())
+        .map(count_fds)
+        .sum();
+    Some(ResourceUsage {
+        rss_bytes,
+        cpu_seconds: cpu_usec as f64 / 1e6,
+        open_fds: Some(open_fds),
+    })
+}
+
+fn count_fds(pid: i32) -> u64 {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|fds| fds.count() as u64)
+        .unwrap_or_default()
+}
+
+// Fields of /proc/<pid>/stat from the state on (field 3), the command name may hold spaces
+fn stat_fields(pid: i32) -> Option<Vec<String>> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, rest) = stat.rsplit_once(')')?;
+    let fields: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+    (fields.len() > 12).then_some(fields)
+}
+
+pub fn parent_pid(pid: i32) -> Option<i32> {
+    stat_fields(pid)?.get(1)?.parse().ok()
+}
+
+// `root` and every process below it, empty when `root` is gone
+fn descendants(root: i32) -> Vec<i32> {
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    let mut root_alive = false;
+    for entry in std::fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<i32>() else {
+            continue;
+        };
+        root_alive |= pid == root;
+        if let Some(ppid) = parent_pid(pid) {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    if !root_alive {
+        return Vec::new();
+    }
+    let mut tree = vec![root];
+    let mut next = 0;
+    while next < tree.len() {
+        tree.extend(children.get(&tree[next]).into_iter().flatten());
+        next += 1;
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sums_the_process_tree() {
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "sleep 5 & sleep 5"])
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let pid = child.id().unwrap() as i32;
+
+        assert_eq!(descendants(pid).len(), 3);
+        let usage = sample(None, Some(pid)).unwrap();
+        assert!(usage.rss_bytes > 0);
+        assert!(usage.open_fds.unwrap() >= 3);
+
+        child.kill().await.unwrap();
+        child.wait().await.unwrap();
+    }
+}
@@ -0,0 +1,1129 @@
+use crate::logs::WORKER_LOGS;
+use crate::scheduler::Resources;
+use crate::telemetry::{LogFormat, OtlpConfig, OtlpProtocol};
+use anyhow::{Context, bail};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+// Settings come from the TOML file, then env vars, then flags; later layers win. Backend
+// sections only have flags for their most common settings.
+#[derive(Parser, Debug, Default)]
+#[command(version, about = "Restate backed browser session orchestrator")]
+pub struct Cli {
+    /// TOML config file
+    #[arg(long, env = "ORCHESTRATOR_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Print the effective config, secrets redacted, and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Axum API listen address
+    #[arg(long, env = "ORCHESTRATOR_LISTEN")]
+    listen: Option<SocketAddr>,
+    /// Restate service endpoint listen address
+    #[arg(long, env = "ORCHESTRATOR_RESTATE_LISTEN")]
+    restate_listen: Option<SocketAddr>,
+    #[arg(long, env = "RESTATE_INGRESS_URL")]
+    restate_ingress_url: Option<String>,
+    #[arg(long, env = "RESTATE_ADMIN_URL")]
+    restate_admin_url: Option<String>,
+
+    /// Where workers run: local, container, kubernetes, chromium or mock
+    #[arg(long, env = "ORCHESTRATOR_WORKER_BACKEND")]
+    worker_backend: Option<String>,
+    /// Worker executable, resolved on PATH
+    #[arg(long, env = "ORCHESTRATOR_WORKER_BINARY")]
+    worker_binary: Option<String>,
+    /// Wait between starting a worker and creating its session
+    #[arg(long, env = "ORCHESTRATOR_WORKER_STARTUP_DELAY_MS")]
+    worker_startup_delay_ms: Option<u64>,
+    /// First port handed to workers
+    #[arg(long, env = "ORCHESTRATOR_WORKER_PORT_MIN")]
+    worker_port_min: Option<u16>,
+    /// Last port handed to workers, exclusive
+    #[arg(long, env = "ORCHESTRATOR_WORKER_PORT_MAX")]
+    worker_port_max: Option<u16>,
+    /// Lines of stdout/stderr kept in memory per worker
+    #[arg(long, env = "ORCHESTRATOR_WORKER_LOG_LINES")]
+    worker_log_lines: Option<usize>,
+    /// Directory worker logs are also written to
+    #[arg(long, env = "ORCHESTRATOR_WORKER_LOG_DIR")]
+    worker_log_dir: Option<PathBuf>,
+    /// Size at which a worker log file is rotated
+    #[arg(long, env = "ORCHESTRATOR_WORKER_LOG_MAX_BYTES")]
+    worker_log_max_bytes: Option<u64>,
+    /// Sessions a worker serves at the same time
+    #[arg(long, env = "ORCHESTRATOR_WORKER_SESSION_CAPACITY")]
+    worker_session_capacity: Option<u64>,
+
+    /// Worker image for the container backend
+    #[arg(long, env = "ORCHESTRATOR_CONTAINER_IMAGE")]
+    container_image: Option<String>,
+    /// Docker or Podman API socket for the container backend
+    #[arg(long, env = "ORCHESTRATOR_CONTAINER_SOCKET")]
+    container_socket: Option<PathBuf>,
+
+    /// Kubernetes API server for the kubernetes backend
+    #[arg(long, env = "ORCHESTRATOR_KUBERNETES_API_URL")]
+    kubernetes_api_url: Option<String>,
+    /// Namespace worker pods are created in
+    #[arg(long, env = "ORCHESTRATOR_KUBERNETES_NAMESPACE")]
+    kubernetes_namespace: Option<String>,
+    /// Pod manifest (JSON) worker pods are created from
+    #[arg(long, env = "ORCHESTRATOR_KUBERNETES_POD_TEMPLATE")]
+    kubernetes_pod_template: Option<PathBuf>,
+
+    /// Chromium executable for the chromium backend, resolved on PATH
+    #[arg(long, env = "ORCHESTRATOR_CHROMIUM_BINARY")]
+    chromium_binary: Option<String>,
+    /// Directory holding each chromium worker's user-data-dir
+    #[arg(long, env = "ORCHESTRATOR_CHROMIUM_DATA_DIR")]
+    chromium_data_dir: Option<PathBuf>,
+
+    /// Delegated cgroup v2 directory each local worker gets a child cgroup in
+    #[arg(long, env = "ORCHESTRATOR_LIMITS_CGROUP_ROOT")]
+    limits_cgroup_root: Option<PathBuf>,
+    /// memory.max of each local worker's cgroup
+    #[arg(long, env = "ORCHESTRATOR_LIMITS_MEMORY_BYTES")]
+    limits_memory_bytes: Option<u64>,
+    /// Idle, ready-checked workers kept for new sessions
+    #[arg(long, env = "ORCHESTRATOR_WARM_POOL_SIZE")]
+    warm_pool_size: Option<usize>,
+    /// Sessions a worker serves before it is retired, 0 for no limit
+    #[arg(long, env = "ORCHESTRATOR_RECYCLE_MAX_SESSIONS")]
+    recycle_max_sessions: Option<u64>,
+    /// How sessions are placed on workers: spread, binpack or least_loaded
+    #[arg(long, env = "ORCHESTRATOR_SCHEDULER_STRATEGY")]
+    scheduler_strategy: Option<String>,
+    /// Memory a worker may use past budget.grace_secs before it is killed
+    #[arg(long, env = "ORCHESTRATOR_BUDGET_MEMORY_BYTES")]
+    budget_memory_bytes: Option<u64>,
+
+    /// Time since creation after which the poller reaps a session
+    #[arg(long, env = "ORCHESTRATOR_SESSION_TTL_SECS")]
+    session_ttl_secs: Option<u64>,
+    /// How long a session's history is kept after it ended
+    #[arg(long, env = "ORCHESTRATOR_HISTORY_RETENTION_SECS")]
+    history_retention_secs: Option<u64>,
+
+    /// Time between reconciliation passes, 0 only reconciles at startup
+    #[arg(long, env = "ORCHESTRATOR_RECONCILE_INTERVAL_SECS")]
+    reconcile_interval_secs: Option<u64>,
+    /// What to do with running workers the pool doesn't know: kill or adopt
+    #[arg(long, env = "ORCHESTRATOR_RECONCILE_UNKNOWN_WORKERS")]
+    reconcile_unknown_workers: Option<String>,
+    /// Time between worker health, usage and budget passes, 0 disables them
+    #[arg(long, env = "ORCHESTRATOR_POLL_WORKERS_INTERVAL_SECS")]
+    poll_workers_interval_secs: Option<u64>,
+    /// Time between session TTL passes, 0 disables them
+    #[arg(long, env = "ORCHESTRATOR_POLL_SESSIONS_INTERVAL_SECS")]
+    poll_sessions_interval_secs: Option<u64>,
+
+    /// Time in-flight requests get to finish after SIGTERM
+    #[arg(long, env = "ORCHESTRATOR_SHUTDOWN_GRACE_SECS")]
+    shutdown_grace_secs: Option<u64>,
+    /// What happens to workers on shutdown: keep them for a successor or terminate them
+    #[arg(long, env = "ORCHESTRATOR_SHUTDOWN_WORKERS")]
+    shutdown_workers: Option<String>,
+
+    /// Shared secret signing Axum to Restate calls
+    #[arg(long, env = "ORCHESTRATOR_INGRESS_SECRET", hide_env_values = true)]
+    ingress_secret: Option<String>,
+    /// Restate server public key requests must be signed with
+    #[arg(long, env = "RESTATE_IDENTITY_KEY")]
+    restate_identity_key: Option<String>,
+    /// JSON file with the static API keys
+    #[arg(long, env = "ORCHESTRATOR_API_KEYS_FILE")]
+    api_keys_file: Option<PathBuf>,
+    /// JWKS file path or http(s) URL
+    #[arg(long, env = "ORCHESTRATOR_JWKS")]
+    jwks: Option<String>,
+    #[arg(long, env = "ORCHESTRATOR_JWT_ISSUER")]
+    jwt_issuer: Option<String>,
+    #[arg(long, env = "ORCHESTRATOR_JWT_AUDIENCE")]
+    jwt_audience: Option<String>,
+
+    /// json or pretty
+    #[arg(long, env = "ORCHESTRATOR_LOG_FORMAT")]
+    log_format: Option<String>,
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+    /// grpc or http/protobuf
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_PROTOCOL")]
+    otlp_protocol: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub worker: WorkerConfig,
+    pub container: ContainerConfig,
+    pub kubernetes: KubernetesConfig,
+    pub chromium: ChromiumConfig,
+    pub mock: MockConfig,
+    pub limits: LimitsConfig,
+    pub budget: BudgetConfig,
+    pub warm_pool: WarmPoolConfig,
+    pub recycle: RecycleConfig,
+    pub scheduler: SchedulerConfig,
+    pub session: SessionConfig,
+    pub reconcile: ReconcileConfig,
+    pub poll: PollConfig,
+    pub shutdown: ShutdownConfig,
+    pub auth: AuthConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen: SocketAddr,
+    pub restate_listen: SocketAddr,
+    pub restate_ingress_url: String,
+    pub restate_admin_url: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen: ([127, 0, 0, 1], 3000).into(),
+            restate_listen: ([127, 0, 0, 1], 4000).into(),
+            restate_ingress_url: "http://127.0.0.1:8080".to_string(),
+            restate_admin_url: "http://127.0.0.1:9070".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    // steel-browser child processes
+    #[default]
+    Local,
+    // steel-browser containers through the Docker or Podman API
+    Container,
+    // steel-browser pods through the Kubernetes API
+    Kubernetes,
+    // Headless Chromium processes, the orchestrator serves their session API over CDP
+    Chromium,
+    // In-process fake workers, for tests
+    Mock,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownWorkers {
+    #[default]
+    Kill,
+    // Take them into the pool along with the session they report
+    Adopt,
+}
+
+impl UnknownWorkers {
+    pub fn parse(raw: &str) -> Option<UnknownWorkers> {
+        match raw {
+            "kill" => Some(UnknownWorkers::Kill),
+            "adopt" => Some(UnknownWorkers::Adopt),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutdownWorkers {
+    // Left running, a successor on the same Restate state picks them up
+    #[default]
+    Keep,
+    Terminate,
+}
+
+impl ShutdownWorkers {
+    pub fn parse(raw: &str) -> Option<ShutdownWorkers> {
+        match raw {
+            "keep" => Some(ShutdownWorkers::Keep),
+            "terminate" => Some(ShutdownWorkers::Terminate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    // Across nodes first, then onto the least loaded worker of the emptiest node
+    Spread,
+    // Onto the fullest worker that still fits, keeping idle workers free
+    #[default]
+    Binpack,
+    LeastLoaded,
+}
+
+impl Strategy {
+    pub fn parse(raw: &str) -> Option<Strategy> {
+        match raw {
+            "spread" => Some(Strategy::Spread),
+            "binpack" => Some(Strategy::Binpack),
+            "least_loaded" => Some(Strategy::LeastLoaded),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Strategy::Spread => "spread",
+            Strategy::Binpack => "binpack",
+            Strategy::LeastLoaded => "least_loaded",
+        }
+    }
+}
+
+impl BackendKind {
+    pub fn parse(raw: &str) -> Option<BackendKind> {
+        match raw {
+            "local" => Some(BackendKind::Local),
+            "container" => Some(BackendKind::Container),
+            "kubernetes" => Some(BackendKind::Kubernetes),
+            "chromium" => Some(BackendKind::Chromium),
+            "mock" => Some(BackendKind::Mock),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerConfig {
+    pub backend: BackendKind,
+    pub binary: String,
+    pub startup_delay_ms: u64,
+    pub port_min: u16,
+    pub port_max: u16,
+    pub log_lines: usize,
+    pub log_dir: Option<PathBuf>,
+    pub log_max_bytes: u64,
+    // Concurrent sessions per worker, recorded on the worker when it starts
+    pub session_capacity: u64,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        WorkerConfig {
+            backend: BackendKind::default(),
+            binary: "steel-browser".to_string(),
+            startup_delay_ms: 500,
+            port_min: 3000,
+            port_max: u16::MAX,
+            log_lines: 1000,
+            log_dir: None,
+            log_max_bytes: 10 * 1024 * 1024,
+            session_capacity: 1,
+        }
+    }
+}
+
+impl WorkerConfig {
+    pub fn startup_delay(&self) -> Duration {
+        Duration::from_millis(self.startup_delay_ms)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContainerConfig {
+    pub socket: PathBuf,
+    pub image: String,
+    // Port steel-browser listens on inside the container
+    pub port: u16,
+    // Attach to this network and reach workers by container name instead of publishing
+    // their port on the host
+    pub network: Option<String>,
+    pub memory_bytes: Option<i64>,
+    pub cpus: Option<f64>,
+    pub pids_limit: Option<i64>,
+}
+
+impl Default for ContainerConfig {
+    fn default() -> Self {
+        ContainerConfig {
+            socket: PathBuf::from("/var/run/docker.sock"),
+            image: "steel-browser:latest".to_string(),
+            port: 3000,
+            network: None,
+            memory_bytes: None,
+            cpus: None,
+            pids_limit: None,
+        }
+    }
+}
+
+// Limits for workers running as local processes (local and chromium backends), the
+// container and kubernetes backends have their own
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    // Must be writable and have the memory, cpu and pids controllers in cgroup.subtree_control
+    pub cgroup_root: Option<PathBuf>,
+    pub memory_bytes: Option<u64>,
+    pub cpus: Option<f64>,
+    pub pids_limit: Option<u64>,
+    // RLIMIT_NOFILE, applied without a cgroup too
+    pub open_files: Option<u64>,
+}
+
+impl LimitsConfig {
+    pub fn needs_cgroup(&self) -> bool {
+        self.memory_bytes.is_some() || self.cpus.is_some() || self.pids_limit.is_some()
+    }
+}
+
+// Soft limits enforced from usage samples, for any backend that reports usage
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    pub memory_bytes: Option<u64>,
+    // How long a worker may stay over budget before it is killed
+    pub grace_secs: u64,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        BudgetConfig {
+            memory_bytes: None,
+            grace_secs: 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WarmPoolConfig {
+    // Idle workers kept ready for new sessions, 0 disables the warm pool
+    pub size: usize,
+    // A warm worker that doesn't answer its probes by then is stopped
+    pub ready_timeout_secs: u64,
+}
+
+impl Default for WarmPoolConfig {
+    fn default() -> Self {
+        WarmPoolConfig {
+            size: 0,
+            ready_timeout_secs: 30,
+        }
+    }
+}
+
+// Where new sessions go. Workers record the labels and resources in effect when they
+// started.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    pub strategy: Strategy,
+    // Sessions select workers on these
+    pub worker_labels: BTreeMap<String, String>,
+    // What a worker offers to its sessions' resource requests
+    pub worker_resources: Resources,
+}
+
+// When a worker is retired instead of taking another session. Busy workers finish their
+// sessions first.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecycleConfig {
+    // Sessions a worker serves before it retires, 0 for no limit. 1 stops every worker
+    // with its session.
+    pub max_sessions: u64,
+    pub max_age_secs: Option<u64>,
+    pub memory_bytes: Option<u64>,
+}
+
+impl Default for RecycleConfig {
+    fn default() -> Self {
+        RecycleConfig {
+            max_sessions: 1,
+            max_age_secs: None,
+            memory_bytes: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KubernetesConfig {
+    pub api_url: String,
+    pub namespace: String,
+    // Service account credentials, used when the files exist
+    pub token_file: PathBuf,
+    pub ca_file: PathBuf,
+    // JSON Pod manifest. Its container named "worker", or its first one, runs steel-browser.
+    pub pod_template: Option<PathBuf>,
+    // Used when there's no template or its worker container has no image
+    pub image: String,
+    pub port: u16,
+    // Put a Service in front of each pod and route through its DNS name instead of the pod IP
+    pub service: bool,
+    pub ready_timeout_secs: u64,
+}
+
+impl Default for KubernetesConfig {
+    fn default() -> Self {
+        KubernetesConfig {
+            api_url: "https://kubernetes.default.svc".to_string(),
+            namespace: "default".to_string(),
+            token_file: PathBuf::from("/var/run/secrets/kubernetes.io/serviceaccount/token"),
+            ca_file: PathBuf::from("/var/run/secrets/kubernetes.io/serviceaccount/ca.crt"),
+            pod_template: None,
+            image: "steel-browser:latest".to_string(),
+            port: 3000,
+            service: false,
+            ready_timeout_secs: 60,
+        }
+    }
+}
+
+impl KubernetesConfig {
+    pub fn ready_timeout(&self) -> Duration {
+        Duration::from_secs(self.ready_timeout_secs)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChromiumConfig {
+    pub binary: String,
+    // Passed before the debugging port and user-data-dir the backend sets itself
+    pub flags: Vec<String>,
+    pub data_dir: PathBuf,
+    // Where the orchestrator serves the session API on behalf of its browsers. A fixed port
+    // keeps journaled endpoints valid across restarts.
+    pub shim_listen: SocketAddr,
+    pub startup_timeout_secs: u64,
+}
+
+impl Default for ChromiumConfig {
+    fn default() -> Self {
+        ChromiumConfig {
+            binary: "chromium".to_string(),
+            flags: vec![
+                "--headless=new".to_string(),
+                "--no-first-run".to_string(),
+                "--no-default-browser-check".to_string(),
+            ],
+            data_dir: std::env::temp_dir().join("browser-orchestrator-chromium"),
+            shim_listen: SocketAddr::from(([127, 0, 0, 1], 4100)),
+            startup_timeout_secs: 10,
+        }
+    }
+}
+
+impl ChromiumConfig {
+    pub fn startup_timeout(&self) -> Duration {
+        Duration::from_secs(self.startup_timeout_secs)
+    }
+}
+
+// Faults the mock backend injects into the workers it spawns next
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockConfig {
+    // Workers only start answering after this long
+    pub slow_start_ms: u64,
+    // Workers stop answering this long after they started
+    pub crash_after_ms: Option<u64>,
+    // Every worker endpoint answers 500
+    pub server_errors: bool,
+    // JSON endpoints answer with a truncated body
+    pub malformed_json: bool,
+    // Memory use the backend reports for every worker, unset reports none
+    pub rss_bytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    pub ttl_secs: u64,
+    pub history_retention_secs: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            ttl_secs: 60,
+            history_retention_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn history_retention(&self) -> Duration {
+        Duration::from_secs(self.history_retention_secs)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconcileConfig {
+    pub interval_secs: u64,
+    pub unknown_workers: UnknownWorkers,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        ReconcileConfig {
+            interval_secs: 60,
+            unknown_workers: UnknownWorkers::Kill,
+        }
+    }
+}
+
+impl ReconcileConfig {
+    pub fn interval(&self) -> Option<Duration> {
+        (self.interval_secs > 0).then(|| Duration::from_secs(self.interval_secs))
+    }
+}
+
+// How often the pool's periodic handlers are invoked, 0 pauses one until a reload sets it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollConfig {
+    // Health checks, usage sampling, the memory budget and age or memory recycling
+    pub workers_interval_secs: u64,
+    // Reaping sessions past session.ttl_secs, a session may outlive its TTL by this much
+    pub sessions_interval_secs: u64,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        PollConfig {
+            workers_interval_secs: 15,
+            sessions_interval_secs: 10,
+        }
+    }
+}
+
+impl PollConfig {
+    pub fn workers_interval(&self) -> Option<Duration> {
+        (self.workers_interval_secs > 0).then(|| Duration::from_secs(self.workers_interval_secs))
+    }
+
+    pub fn sessions_interval(&self) -> Option<Duration> {
+        (self.sessions_interval_secs > 0).then(|| Duration::from_secs(self.sessions_interval_secs))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    pub grace_secs: u64,
+    pub workers: ShutdownWorkers,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig {
+            grace_secs: 30,
+            workers: ShutdownWorkers::Keep,
+        }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn grace(&self) -> Duration {
+        Duration::from_secs(self.grace_secs)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub ingress_secret: Option<String>,
+    pub restate_identity_key: Option<String>,
+    pub api_keys_file: Option<PathBuf>,
+    pub jwks: Option<String>,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub log_format: LogFormat,
+    // Spans are only exported when an OTLP endpoint is configured
+    pub otlp_endpoint: Option<String>,
+    pub otlp_protocol: OtlpProtocol,
+}
+
+impl TelemetryConfig {
+    pub fn otlp(&self) -> Option<OtlpConfig> {
+        self.otlp_endpoint.as_ref().map(|endpoint| OtlpConfig {
+            endpoint: endpoint.clone(),
+            protocol: self.otlp_protocol,
+        })
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *field = value;
+    }
+}
+
+fn set_opt<T>(field: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *field = value;
+    }
+}
+
+impl Config {
+    // TOML file, then env vars and flags as merged by clap, then validation
+    pub fn load(cli: Cli) -> anyhow::Result<Config> {
+        let mut config = match &cli.config {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config {}", path.display()))?;
+                toml::from_str(&raw)
+                    .with_context(|| format!("Invalid config {}", path.display()))?
+            }
+            None => Config::default(),
+        };
+        config.apply(cli)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        let server = &mut self.server;
+        set(&mut server.listen, cli.listen);
+        set(&mut server.restate_listen, cli.restate_listen);
+        set(&mut server.restate_ingress_url, cli.restate_ingress_url);
+        set(&mut server.restate_admin_url, cli.restate_admin_url);
+
+        let worker = &mut self.worker;
+        if let Some(raw) = cli.worker_backend {
+            worker.backend = BackendKind::parse(&raw)
+                .ok_or_else(|| anyhow::anyhow!("Invalid worker backend: {raw}"))?;
+        }
+        set(&mut worker.binary, cli.worker_binary);
+        set(&mut worker.startup_delay_ms, cli.worker_startup_delay_ms);
+        set(&mut worker.port_min, cli.worker_port_min);
+        set(&mut worker.port_max, cli.worker_port_max);
+        set(&mut worker.log_lines, cli.worker_log_lines);
+        set_opt(&mut worker.log_dir, cli.worker_log_dir);
+        set(&mut worker.log_max_bytes, cli.worker_log_max_bytes);
+        set(&mut worker.session_capacity, cli.worker_session_capacity);
+
+        set(&mut self.container.image, cli.container_image);
+        set(&mut self.container.socket, cli.container_socket);
+
+        let kubernetes = &mut self.kubernetes;
+        set(&mut kubernetes.api_url, cli.kubernetes_api_url);
+        set(&mut kubernetes.namespace, cli.kubernetes_namespace);
+        set_opt(&mut kubernetes.pod_template, cli.kubernetes_pod_template);
+
+        set(&mut self.chromium.binary, cli.chromium_binary);
+        set(&mut self.chromium.data_dir, cli.chromium_data_dir);
+
+        set_opt(&mut self.limits.cgroup_root, cli.limits_cgroup_root);
+        set_opt(&mut self.limits.memory_bytes, cli.limits_memory_bytes);
+        set_opt(&mut self.budget.memory_bytes, cli.budget_memory_bytes);
+        set(&mut self.warm_pool.size, cli.warm_pool_size);
+        set(&mut self.recycle.max_sessions, cli.recycle_max_sessions);
+        if let Some(raw) = cli.scheduler_strategy {
+            self.scheduler.strategy = Strategy::parse(&raw)
+                .ok_or_else(|| anyhow::anyhow!("Invalid scheduler strategy: {raw}"))?;
+        }
+
+        set(&mut self.session.ttl_secs, cli.session_ttl_secs);
+        set(
+            &mut self.session.history_retention_secs,
+            cli.history_retention_secs,
+        );
+
+        set(
+            &mut self.reconcile.interval_secs,
+            cli.reconcile_interval_secs,
+        );
+        if let Some(raw) = cli.reconcile_unknown_workers {
+            self.reconcile.unknown_workers = UnknownWorkers::parse(&raw)
+                .ok_or_else(|| anyhow::anyhow!("Invalid reconcile unknown_workers: {raw}"))?;
+        }
+        set(
+            &mut self.poll.workers_interval_secs,
+            cli.poll_workers_interval_secs,
+        );
+        set(
+            &mut self.poll.sessions_interval_secs,
+            cli.poll_sessions_interval_secs,
+        );
+
+        set(&mut self.shutdown.grace_secs, cli.shutdown_grace_secs);
+        if let Some(raw) = cli.shutdown_workers {
+            self.shutdown.workers = ShutdownWorkers::parse(&raw)
+                .ok_or_else(|| anyhow::anyhow!("Invalid shutdown workers: {raw}"))?;
+        }
+
+        let auth = &mut self.auth;
+        set_opt(&mut auth.ingress_secret, cli.ingress_secret);
+        set_opt(&mut auth.restate_identity_key, cli.restate_identity_key);
+        set_opt(&mut auth.api_keys_file, cli.api_keys_file);
+        set_opt(&mut auth.jwks, cli.jwks);
+        set_opt(&mut auth.jwt_issuer, cli.jwt_issuer);
+        set_opt(&mut auth.jwt_audience, cli.jwt_audience);
+
+        let telemetry = &mut self.telemetry;
+        if let Some(raw) = cli.log_format {
+            telemetry.log_format = LogFormat::parse(&raw)
+                .ok_or_else(|| anyhow::anyhow!("Invalid log format: {raw}"))?;
+        }
+        set_opt(&mut telemetry.otlp_endpoint, cli.otlp_endpoint);
+        if let Some(raw) = cli.otlp_protocol {
+            telemetry.otlp_protocol = OtlpProtocol::parse(&raw)
+                .ok_or_else(|| anyhow::anyhow!("Invalid OTLP protocol: {raw}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.server.listen == self.server.restate_listen {
+            bail!("server.listen and server.restate_listen must differ");
+        }
+        for (name, url) in [
+            (
+                "server.restate_ingress_url",
+                &self.server.restate_ingress_url,
+            ),
+            ("server.restate_admin_url", &self.server.restate_admin_url),
+            ("kubernetes.api_url", &self.kubernetes.api_url),
+        ] {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                bail!("{name} must be an http(s) URL, got {url}");
+            }
+        }
+        if self.worker.binary.is_empty() {
+            bail!("worker.binary must not be empty");
+        }
+        if self.worker.port_min == 0 || self.worker.port_min >= self.worker.port_max {
+            bail!(
+                "worker.port_min must be non-zero and below worker.port_max, got {}..{}",
+                self.worker.port_min,
+                self.worker.port_max
+            );
+        }
+        if self.worker.log_lines == 0 || self.worker.log_max_bytes == 0 {
+            bail!("worker.log_lines and worker.log_max_bytes must be positive");
+        }
+        if self.worker.session_capacity == 0 {
+            bail!("worker.session_capacity must be positive");
+        }
+        // A worker stops taking sessions once it served max_sessions, below the capacity it
+        // would never fill up
+        if self.recycle.max_sessions > 0 && self.recycle.max_sessions < self.worker.session_capacity
+        {
+            bail!("recycle.max_sessions must be 0 or at least worker.session_capacity");
+        }
+        if self.worker.backend == BackendKind::Container {
+            if self.container.image.is_empty() || self.container.port == 0 {
+                bail!("container.image and container.port must be set");
+            }
+            if self.container.cpus.is_some_and(|c| c <= 0.0) {
+                bail!("container.cpus must be positive");
+            }
+        }
+        if self.worker.backend == BackendKind::Kubernetes {
+            if self.kubernetes.namespace.is_empty() || self.kubernetes.port == 0 {
+                bail!("kubernetes.namespace and kubernetes.port must be set");
+            }
+            if self.kubernetes.ready_timeout_secs == 0 {
+                bail!("kubernetes.ready_timeout_secs must be positive");
+            }
+        }
+        if self.worker.backend == BackendKind::Chromium {
+            if self.chromium.binary.is_empty() || self.chromium.startup_timeout_secs == 0 {
+                bail!("chromium.binary and chromium.startup_timeout_secs must be set");
+            }
+            if [self.server.listen, self.server.restate_listen].contains(&self.chromium.shim_listen)
+            {
+                bail!("chromium.shim_listen must differ from the server addresses");
+            }
+        }
+        if self.limits.needs_cgroup() && self.limits.cgroup_root.is_none() {
+            bail!("limits.memory_bytes, limits.cpus and limits.pids_limit need limits.cgroup_root");
+        }
+        if self.limits.cpus.is_some_and(|c| c <= 0.0) || self.limits.open_files == Some(0) {
+            bail!("limits.cpus and limits.open_files must be positive");
+        }
+        if self.warm_pool.size > 0 && self.warm_pool.ready_timeout_secs == 0 {
+            bail!("warm_pool.ready_timeout_secs must be positive");
+        }
+        if self.recycle.max_age_secs == Some(0) || self.recycle.memory_bytes == Some(0) {
+            bail!("recycle.max_age_secs and recycle.memory_bytes must be positive");
+        }
+        let offered = &self.scheduler.worker_resources;
+        if offered.memory_bytes == Some(0) || offered.cpus.is_some_and(|c| c <= 0.0) {
+            bail!("scheduler.worker_resources must be positive");
+        }
+        if self.budget.memory_bytes == Some(0) {
+            bail!("budget.memory_bytes must be positive");
+        }
+        if self.session.ttl_secs == 0 {
+            bail!("session.ttl_secs must be positive");
+        }
+        // Sessions live up to one interval past their TTL, longer would make the TTL moot
+        if self.poll.sessions_interval_secs > self.session.ttl_secs {
+            bail!("poll.sessions_interval_secs must be 0 or at most session.ttl_secs");
+        }
+        if self.auth.ingress_secret.as_deref() == Some("") {
+            bail!("auth.ingress_secret must not be empty when set");
+        }
+        if self.auth.jwks.is_none()
+            && (self.auth.jwt_issuer.is_some() || self.auth.jwt_audience.is_some())
+        {
+            bail!("auth.jwt_issuer and auth.jwt_audience require auth.jwks");
+        }
+        Ok(())
+    }
+
+    // Secrets replaced, for --print-config and GET /admin/config
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        if config.auth.ingress_secret.is_some() {
+            config.auth.ingress_secret = Some("<redacted>".to_string());
+        }
+        config
+    }
+
+    // Applies a JSON merge patch (RFC 7396) on top of this config
+    pub fn patched(&self, patch: Value) -> anyhow::Result<Config> {
+        let mut merged = serde_json::to_value(self)?;
+        merge_patch(&mut merged, patch);
+        Ok(serde_json::from_value(merged)?)
+    }
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// Dotted paths of the leaves that differ, e.g. session.ttl_secs
+fn changed_keys(old: &Value, new: &Value, prefix: &str, out: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                changed_keys(
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    &path,
+                    out,
+                );
+            }
+        }
+        _ if old != new => out.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+// Sections bound at startup, changing them needs a restart
+const RESTART_ONLY: [&str; 11] = [
+    "server.",
+    "auth.",
+    "telemetry.",
+    "worker.backend",
+    "container.socket",
+    "kubernetes.api_url",
+    "kubernetes.ca_file",
+    // Pods already created live in the old one
+    "kubernetes.namespace",
+    "chromium.data_dir",
+    "chromium.shim_listen",
+    // Running workers' cgroups are looked up under it
+    "limits.cgroup_root",
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadOutcome {
+    pub applied: Vec<String>,
+    pub requires_restart: Vec<String>,
+}
+
+// Live config shared by the Restate handlers and Axum. A reload swaps the whole value, so
+// readers see either the old or the new config, never a mix.
+#[derive(Clone, Default)]
+pub struct ConfigHandle {
+    current: Arc<RwLock<Arc<Config>>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Config) -> Self {
+        ConfigHandle {
+            current: Arc::new(RwLock::new(Arc::new(config))),
+        }
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    // Swaps in the runtime tunable settings of `next`. Restart only settings keep their running
+    // value and are reported back, and `next` is validated with them in place since that is
+    // the config that would run.
+    pub fn reload(&self, mut next: Config) -> anyhow::Result<ReloadOutcome> {
+        let mut current = self.current.write().unwrap();
+
+        let mut changed = Vec::new();
+        changed_keys(
+            &serde_json::to_value(&**current)?,
+            &serde_json::to_value(&next)?,
+            "",
+            &mut changed,
+        );
+        let (requires_restart, applied) = changed
+            .into_iter()
+            .partition(|key| RESTART_ONLY.iter().any(|p| key.starts_with(p)));
+        let outcome = ReloadOutcome {
+            applied,
+            requires_restart,
+        };
+
+        next.server = current.server.clone();
+        next.auth = current.auth.clone();
+        next.telemetry = current.telemetry.clone();
+        next.worker.backend = current.worker.backend;
+        next.container.socket = current.container.socket.clone();
+        next.kubernetes.api_url = current.kubernetes.api_url.clone();
+        next.kubernetes.ca_file = current.kubernetes.ca_file.clone();
+        next.kubernetes.namespace = current.kubernetes.namespace.clone();
+        next.chromium.data_dir = current.chromium.data_dir.clone();
+        next.chromium.shim_listen = current.chromium.shim_listen;
+        next.limits.cgroup_root = current.limits.cgroup_root.clone();
+        next.validate()?;
+        WORKER_LOGS.configure(&next.worker);
+        *current = Arc::new(next);
+
+        if !outcome.requires_restart.is_empty() {
+            tracing::warn!(
+                settings = ?outcome.requires_restart,
+                "config changes ignored until restart"
+            );
+        }
+        tracing::info!(settings = ?outcome.applied, "config reloaded");
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn patches_merge_into_the_running_config() {
+        let config = Config::default();
+        let patched = config
+            .patched(json!({
+                "session": { "ttl_secs": 123 },
+                "warm_pool": { "size": 2 },
+            }))
+            .unwrap();
+        assert_eq!(patched.session.ttl_secs, 123);
+        assert_eq!(patched.warm_pool.size, 2);
+        // Untouched siblings keep their value
+        assert_eq!(
+            patched.session.history_retention_secs,
+            config.session.history_retention_secs
+        );
+
+        // null removes the key, which falls back to its default
+        let mut config = Config::default();
+        config.session.ttl_secs = 123;
+        let patched = config
+            .patched(json!({ "session": { "ttl_secs": null } }))
+            .unwrap();
+        assert_eq!(patched.session.ttl_secs, Config::default().session.ttl_secs);
+
+        assert!(config.patched(json!({ "session": { "nope": 1 } })).is_err());
+    }
+
+    #[test]
+    fn lists_the_changed_leaves() {
+        let mut changed = Vec::new();
+        changed_keys(
+            &json!({ "a": { "b": 1, "c": 2 }, "d": [1], "e": "x" }),
+            &json!({ "a": { "b": 1, "c": 3 }, "d": [1, 2], "f": true }),
+            "",
+            &mut changed,
+        );
+        assert_eq!(changed, ["a.c", "d", "e", "f"]);
+    }
+
+    #[test]
+    fn reload_keeps_restart_only_settings() {
+        let handle = ConfigHandle::new(Config::default());
+        let mut next = Config::default();
+        next.session.ttl_secs = 123;
+        next.server.listen = "127.0.0.1:1".parse().unwrap();
+        next.chromium.data_dir = PathBuf::from("/elsewhere");
+
+        let outcome = handle.reload(next).unwrap();
+        assert_eq!(outcome.applied, ["session.ttl_secs"]);
+        assert_eq!(
+            outcome.requires_restart,
+            ["chromium.data_dir", "server.listen"]
+        );
+        let current = handle.get();
+        assert_eq!(current.session.ttl_secs, 123);
+        assert_eq!(current.server.listen, Config::default().server.listen);
+        assert_eq!(
+            current.chromium.data_dir,
+            Config::default().chromium.data_dir
+        );
+    }
+
+    #[test]
+    fn reload_validates_with_the_running_restart_only_settings() {
+        let mut running = Config::default();
+        running.limits.cgroup_root = Some(PathBuf::from("/sys/fs/cgroup/orchestrator"));
+        let handle = ConfigHandle::new(running);
+
+        // Only valid with the cgroup_root that stays in place
+        let mut next = Config::default();
+        next.limits.memory_bytes = Some(1 << 30);
+        let outcome = handle.reload(next).unwrap();
+        assert_eq!(outcome.requires_restart, ["limits.cgroup_root"]);
+        assert_eq!(handle.get().limits.memory_bytes, Some(1 << 30));
+
+        // Invalid whatever the restart only settings
+        let mut next = Config::default();
+        next.session.ttl_secs = 0;
+        assert!(handle.reload(next).is_err());
+        assert_eq!(handle.get().limits.memory_bytes, Some(1 << 30));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::auth::{API_KEY_ID_HEADER, SUBJECT_HEADER};
+use restate_sdk::context::HeaderMap as RestateHeaderMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+pub static EVENTS: LazyLock<EventBus> = LazyLock::new(|| EventBus::new(1024));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    // Placement of the session started, under the id the worker is asked to take
+    SessionCreated,
+    // Where the scheduler put the session and why
+    SessionPlaced,
+    SessionReady,
+    // A client health check of the session, only kept in its history
+    SessionKeepalive,
+    SessionExpired,
+    SessionDeleted,
+    // The session's worker vanished, the session can't be served anymore
+    SessionFailed,
+    WorkerCrashed,
+    // A new worker took the place of one that crashed or retired
+    WorkerRestarted,
+    // A running worker the pool didn't know was taken into it
+    WorkerAdopted,
+    // Stopped under the recycle policy once its sessions ended
+    WorkerRetired,
+}
+
+impl EventKind {
+    pub fn parse(raw: &str) -> Option<EventKind> {
+        match raw {
+            "session_created" => Some(EventKind::SessionCreated),
+            "session_placed" => Some(EventKind::SessionPlaced),
+            "session_ready" => Some(EventKind::SessionReady),
+            "session_keepalive" => Some(EventKind::SessionKeepalive),
+            "session_expired" => Some(EventKind::SessionExpired),
+            "session_deleted" => Some(EventKind::SessionDeleted),
+            "session_failed" => Some(EventKind::SessionFailed),
+            "worker_crashed" => Some(EventKind::WorkerCrashed),
+            "worker_restarted" => Some(EventKind::WorkerRestarted),
+            "worker_adopted" => Some(EventKind::WorkerAdopted),
+            "worker_retired" => Some(EventKind::WorkerRetired),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::SessionCreated => "session_created",
+            EventKind::SessionPlaced => "session_placed",
+            EventKind::SessionReady => "session_ready",
+            EventKind::SessionKeepalive => "session_keepalive",
+            EventKind::SessionExpired => "session_expired",
+            EventKind::SessionDeleted => "session_deleted",
+            EventKind::SessionFailed => "session_failed",
+            EventKind::WorkerCrashed => "worker_crashed",
+            EventKind::WorkerRestarted => "worker_restarted",
+            EventKind::WorkerAdopted => "worker_adopted",
+            EventKind::WorkerRetired => "worker_retired",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct PoolEvent {
+    pub kind: EventKind,
+    pub timestamp: i64,
+    pub session_id: Option<String>,
+    pub worker_id: Option<String>,
+    pub user: Option<String>,
+    pub reason: Option<String>,
+    // Who triggered the transition, unset for the pollers
+    pub actor: Option<String>,
+    pub api_key_id: Option<String>,
+}
+
+impl PoolEvent {
+    pub fn new(kind: EventKind) -> Self {
+        PoolEvent {
+            kind,
+            timestamp: 0,
+            session_id: None,
+            worker_id: None,
+            user: None,
+            reason: None,
+            actor: None,
+            api_key_id: None,
+        }
+    }
+
+    pub fn session(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    pub fn worker(mut self, worker_id: &str) -> Self {
+        self.worker_id = Some(worker_id.to_string());
+        self
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    // Attributes the event to the caller forwarded by the Axum layer
+    pub fn caller(mut self, headers: &RestateHeaderMap) -> Self {
+        self.actor = headers.get(SUBJECT_HEADER).cloned();
+        self.api_key_id = headers.get(API_KEY_ID_HEADER).cloned();
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct EventFilter {
+    pub user: Option<String>,
+    pub session_id: Option<String>,
+    pub kinds: Vec<EventKind>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &PoolEvent) -> bool {
+        let user_ok = self.user.is_none() || self.user == event.user;
+        let session_ok = self.session_id.is_none() || self.session_id == event.session_id;
+        let kind_ok = self.kinds.is_empty() || self.kinds.contains(&event.kind);
+        user_ok && session_ok && kind_ok
+    }
+}
+
+pub struct EventBus {
+    tx: broadcast::Sender<PoolEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        EventBus { tx }
+    }
+
+    // Returns the event as sent, stamped with the publish time
+    pub fn publish(&self, mut event: PoolEvent) -> PoolEvent {
+        event.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        // No subscribers is fine, events are not buffered for late listeners
+        let _ = self.tx.send(event.clone());
+        event
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PoolEvent> {
+        self.tx.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [EventKind; 11] = [
+        EventKind::SessionCreated,
+        EventKind::SessionPlaced,
+        EventKind::SessionReady,
+        EventKind::SessionKeepalive,
+        EventKind::SessionExpired,
+        EventKind::SessionDeleted,
+        EventKind::SessionFailed,
+        EventKind::WorkerCrashed,
+        EventKind::WorkerRestarted,
+        EventKind::WorkerAdopted,
+        EventKind::WorkerRetired,
+    ];
+
+    #[test]
+    fn kinds_parse_from_their_wire_name() {
+        for kind in KINDS {
+            assert_eq!(EventKind::parse(kind.as_str()), Some(kind));
+            // The same name serde uses
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::json!(kind.as_str())
+            );
+        }
+        assert_eq!(EventKind::parse("Session_Ready"), None);
+    }
+
+    #[test]
+    fn filters_match_on_every_field_set() {
+        let event = PoolEvent::new(EventKind::SessionReady)
+            .session("s1")
+            .user("alice");
+        assert!(EventFilter::default().matches(&event));
+
+        let filter =
+            |user: Option<&str>, session_id: Option<&str>, kinds: Vec<EventKind>| EventFilter {
+                user: user.map(str::to_string),
+                session_id: session_id.map(str::to_string),
+                kinds,
+            };
+        assert!(filter(Some("alice"), Some("s1"), vec![EventKind::SessionReady]).matches(&event));
+        assert!(
+            filter(
+                None,
+                None,
+                vec![EventKind::SessionDeleted, EventKind::SessionReady]
+            )
+            .matches(&event)
+        );
+        assert!(!filter(Some("bob"), None, vec![]).matches(&event));
+        assert!(!filter(None, Some("s2"), vec![]).matches(&event));
+        assert!(!filter(None, None, vec![EventKind::SessionDeleted]).matches(&event));
+
+        // Events without a user only reach unfiltered listeners
+        let retired = PoolEvent::new(EventKind::WorkerRetired).worker("w1");
+        assert!(!filter(Some("alice"), None, vec![]).matches(&retired));
+    }
+}
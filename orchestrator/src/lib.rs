@@ -0,0 +1,13 @@
+pub mod api;
+pub mod auth;
+pub mod backend;
+pub mod config;
+pub mod events;
+pub mod logs;
+pub mod metrics;
+pub mod pollers;
+pub mod probes;
+pub mod reconcile;
+pub mod scheduler;
+pub mod shutdown;
+pub mod telemetry;
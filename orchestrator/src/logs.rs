@@ -0,0 +1,299 @@
+use crate::config::WorkerConfig;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+use tokio::sync::{broadcast, mpsc};
+use utoipa::ToSchema;
+
+pub static WORKER_LOGS: LazyLock<LogStore> =
+    LazyLock::new(|| LogStore::from_config(&WorkerConfig::default()));
+
+// Rotated files kept next to the live one: <worker>.log.1 .. <worker>.log.N
+const ROTATED_FILES: usize = 3;
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct LogLine {
+    pub timestamp: i64,
+    pub stream: &'static str,
+    pub line: String,
+}
+
+pub struct WorkerLog {
+    lines: Mutex<VecDeque<LogLine>>,
+    capacity: usize,
+    tx: broadcast::Sender<LogLine>,
+    // The file copy gets every line, unlike followers that may lag behind and skip some
+    file: Option<mpsc::UnboundedSender<LogLine>>,
+}
+
+impl WorkerLog {
+    fn new(capacity: usize, file: Option<mpsc::UnboundedSender<LogLine>>) -> Self {
+        let (tx, _) = broadcast::channel(256);
+        WorkerLog {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            tx,
+            file,
+        }
+    }
+
+    fn push(&self, line: LogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line.clone());
+        if let Some(file) = &self.file {
+            let _ = file.send(line.clone());
+        }
+        // No receivers just means nobody is following
+        let _ = self.tx.send(line);
+    }
+
+    pub fn tail(&self, n: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+
+    // Snapshot and subscription are taken under the same lock so no line is missed or repeated
+    pub fn follow(&self, n: usize) -> (Vec<LogLine>, broadcast::Receiver<LogLine>) {
+        let lines = self.lines.lock().unwrap();
+        let rx = self.tx.subscribe();
+        let tail = lines
+            .iter()
+            .skip(lines.len().saturating_sub(n))
+            .cloned()
+            .collect();
+        (tail, rx)
+    }
+}
+
+#[derive(Clone)]
+struct LogSettings {
+    capacity: usize,
+    log_dir: Option<PathBuf>,
+    max_file_bytes: u64,
+}
+
+pub struct LogStore {
+    workers: Mutex<HashMap<String, Arc<WorkerLog>>>,
+    settings: Mutex<LogSettings>,
+}
+
+impl LogStore {
+    pub fn new(capacity: usize, log_dir: Option<PathBuf>, max_file_bytes: u64) -> Self {
+        LogStore {
+            workers: Mutex::new(HashMap::new()),
+            settings: Mutex::new(LogSettings {
+                capacity,
+                log_dir,
+                max_file_bytes,
+            }),
+        }
+    }
+
+    fn from_config(config: &WorkerConfig) -> Self {
+        LogStore::new(
+            config.log_lines,
+            config.log_dir.clone(),
+            config.log_max_bytes,
+        )
+    }
+
+    // Only applies to workers captured afterwards
+    pub fn configure(&self, config: &WorkerConfig) {
+        *self.settings.lock().unwrap() = LogSettings {
+            capacity: config.log_lines,
+            log_dir: config.log_dir.clone(),
+            max_file_bytes: config.log_max_bytes,
+        };
+    }
+
+    pub fn get(&self, worker_id: &str) -> Option<Arc<WorkerLog>> {
+        self.workers.lock().unwrap().get(worker_id).cloned()
+    }
+
+    pub fn remove(&self, worker_id: &str) {
+        self.workers.lock().unwrap().remove(worker_id);
+    }
+
+    // Takes over the piped stdout and stderr of a freshly spawned worker
+    pub fn capture(&self, worker_id: &str, child: &mut Child) {
+        let settings = self.settings.lock().unwrap().clone();
+        let file = settings.log_dir.as_ref().map(|dir| {
+            let writer = RotatingFile::new(
+                dir.join(format!("{}.log", worker_id)),
+                settings.max_file_bytes,
+            );
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(writer.run(rx));
+            tx
+        });
+        let log = Arc::new(WorkerLog::new(settings.capacity, file));
+        self.workers
+            .lock()
+            .unwrap()
+            .insert(worker_id.to_string(), log.clone());
+
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(read_lines(stdout, "stdout", log.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(read_lines(stderr, "stderr", log));
+        }
+    }
+}
+
+async fn read_lines(pipe: impl AsyncRead + Unpin, stream: &'static str, log: Arc<WorkerLog>) {
+    let mut lines = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        log.push(LogLine {
+            timestamp,
+            stream,
+            line,
+        });
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_bytes: u64) -> Self {
+        RotatingFile { path, max_bytes }
+    }
+
+    async fn open(&self) -> std::io::Result<(File, u64)> {
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        let len = file.metadata().await?.len();
+        Ok((file, len))
+    }
+
+    async fn rotate(&self) -> std::io::Result<()> {
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        for n in (1..ROTATED_FILES).rev() {
+            if tokio::fs::try_exists(rotated(n)).await? {
+                tokio::fs::rename(rotated(n), rotated(n + 1)).await?;
+            }
+        }
+        tokio::fs::rename(&self.path, rotated(1)).await
+    }
+
+    // Runs until the worker's log is dropped, file errors stop the file copy only
+    async fn run(self, mut rx: mpsc::UnboundedReceiver<LogLine>) {
+        let Ok((mut file, mut written)) = self.open().await else {
+            return;
+        };
+        while let Some(line) = rx.recv().await {
+            let entry = format!("{} {} {}\n", line.timestamp, line.stream, line.line);
+            if file.write_all(entry.as_bytes()).await.is_err() {
+                return;
+            }
+            written += entry.len() as u64;
+            if written >= self.max_bytes {
+                if self.rotate().await.is_err() {
+                    return;
+                }
+                let Ok((next, len)) = self.open().await else {
+                    return;
+                };
+                file = next;
+                written = len;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(n: usize) -> LogLine {
+        LogLine {
+            timestamp: 0,
+            stream: "stdout",
+            line: format!("line {n}"),
+        }
+    }
+
+    fn texts(lines: Vec<LogLine>) -> Vec<String> {
+        lines.into_iter().map(|l| l.line).collect()
+    }
+
+    #[test]
+    fn keeps_only_the_last_lines() {
+        let log = WorkerLog::new(3, None);
+        for n in 0..5 {
+            log.push(line(n));
+        }
+        assert_eq!(texts(log.tail(10)), ["line 2", "line 3", "line 4"]);
+        assert_eq!(texts(log.tail(2)), ["line 3", "line 4"]);
+        assert!(log.tail(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn followers_get_the_backlog_then_new_lines() {
+        let log = WorkerLog::new(10, None);
+        log.push(line(0));
+        log.push(line(1));
+        let (backlog, mut rx) = log.follow(1);
+        assert_eq!(texts(backlog), ["line 1"]);
+        log.push(line(2));
+        assert_eq!(rx.recv().await.unwrap().line, "line 2");
+    }
+
+    #[tokio::test]
+    async fn the_file_copy_rotates_and_misses_no_line() {
+        let dir = std::env::temp_dir().join(format!("logs-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("w1.log");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(RotatingFile::new(path.clone(), 64).run(rx));
+        let log = WorkerLog::new(2, Some(tx));
+        // Far more than a follower's broadcast buffer holds
+        for n in 0..1000 {
+            log.push(line(n));
+        }
+        drop(log);
+        writer.await.unwrap();
+
+        let read = |suffix: &str| {
+            std::fs::read_to_string(format!("{}{}", path.display(), suffix)).unwrap_or_default()
+        };
+        // Rotated once over 64 bytes, only the last few files are kept
+        assert!(read(".4").is_empty());
+        let kept: Vec<String> = [".3", ".2", ".1", ""]
+            .iter()
+            .flat_map(|suffix| read(suffix).lines().map(str::to_string).collect::<Vec<_>>())
+            .collect();
+        assert!(read(".1").len() >= 64);
+        assert!(read("").len() < 64);
+        // The kept files hold an unbroken run ending with the last line
+        let expected: Vec<String> = (1000 - kept.len()..1000)
+            .map(|n| format!("0 stdout line {n}"))
+            .collect();
+        assert_eq!(kept, expected);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
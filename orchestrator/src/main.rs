@@ -1,33 +1,118 @@
-pub mod api;
-
-use api::WorkerPoolService;
+use browser_orchestrator::api::{self, WorkerPoolService};
+use browser_orchestrator::auth::Authenticator;
+use browser_orchestrator::config::{Cli, Config, ConfigHandle, ShutdownWorkers};
+use browser_orchestrator::logs::WORKER_LOGS;
+use browser_orchestrator::shutdown::{self, LIFECYCLE};
+use browser_orchestrator::{backend, pollers, reconcile, telemetry};
+use clap::Parser;
 use restate_sdk::prelude::*;
+use std::sync::Arc;
 use tokio::net::TcpListener;
+use tokio::signal::unix::{SignalKind, signal};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let restate_handle = tokio::spawn(async {
-        HttpServer::new(
-            Endpoint::builder()
-                .bind(api::Pool::default().serve())
-                .build(),
-        )
-        .listen_and_serve("127.0.0.1:4000".parse().unwrap())
-        .await;
+    let cli = Cli::parse();
+    let print_config = cli.print_config;
+    let config = Config::load(cli)?;
+    if print_config {
+        print!("{}", toml::to_string_pretty(&config.redacted())?);
+        return Ok(());
+    }
+
+    let tracer_provider = telemetry::init(
+        config.telemetry.log_format,
+        config.telemetry.otlp().as_ref(),
+    )?;
+    WORKER_LOGS.configure(&config.worker);
+
+    let handle = ConfigHandle::new(config.clone());
+    tokio::spawn(reload_on_hangup(handle.clone()));
+
+    let worker_backend = backend::from_config(&handle)?;
+    match worker_backend.cleanup_orphans().await {
+        Ok(0) => {}
+        Ok(removed) => tracing::info!(removed, "cleaned up orphaned workers"),
+        Err(e) => tracing::warn!(error = %format!("{e:#}"), "orphan cleanup failed"),
+    }
+    tokio::spawn(reconcile::run(handle.clone()));
+    pollers::spawn(&handle);
+
+    let mut endpoint = Endpoint::builder()
+        .bind(api::WorkerPool::new(handle.clone(), worker_backend.clone()).serve());
+    // Only accept requests signed by the Restate server holding the matching private key
+    if let Some(key) = &config.auth.restate_identity_key {
+        endpoint = endpoint
+            .identity_key(key)
+            .map_err(|e| anyhow::anyhow!("Invalid restate_identity_key: {e}"))?;
+    }
+    let endpoint = endpoint.build();
+    let restate_listener = TcpListener::bind(config.server.restate_listen).await?;
+    // Outlives the Axum server so requests in flight through the ingress can finish
+    let (stop_restate, restate_stopped) = tokio::sync::oneshot::channel::<()>();
+    let mut restate_handle = tokio::spawn(async move {
+        HttpServer::new(endpoint)
+            .serve_with_cancel(restate_listener, restate_stopped)
+            .await;
     });
 
-    let restate_ingress = "http://127.0.0.1:8080".to_string();
-    let listener = TcpListener::bind("127.0.0.1:3000").await?;
-    let axum_handle = tokio::spawn(async move {
-        axum::serve(listener, api::router(restate_ingress))
+    let authenticator = Arc::new(Authenticator::from_config(&config.auth).await?);
+    let listener = TcpListener::bind(config.server.listen).await?;
+    tracing::info!(addr = %config.server.listen, "axum listening");
+    let router = api::router(handle.clone(), authenticator);
+    let mut axum_handle = tokio::spawn(async move {
+        axum::serve(listener, router)
+            .with_graceful_shutdown(LIFECYCLE.shutting_down())
             .await
             .expect("axum server failed");
     });
-    // Run restate + axum in background
-    tokio::select! {
-        _ = restate_handle => {},
-        _ = axum_handle => {},
+    tokio::spawn(shutdown::on_signal());
+    // Run restate + axum in background until a signal asks to shut down
+    // Biased since a graceful Axum shutdown completes together with the signal
+    let shutting_down = tokio::select! {
+        biased;
+        _ = LIFECYCLE.shutting_down() => true,
+        _ = &mut restate_handle => false,
+        _ = &mut axum_handle => false,
+    };
+
+    if shutting_down {
+        let current = handle.get();
+        if tokio::time::timeout(current.shutdown.grace(), axum_handle)
+            .await
+            .is_err()
+        {
+            tracing::warn!("grace period elapsed with requests still in flight");
+        }
+        match current.shutdown.workers {
+            ShutdownWorkers::Keep => tracing::info!("leaving workers running for a successor"),
+            ShutdownWorkers::Terminate => {
+                shutdown::terminate_workers(&current, worker_backend.as_ref()).await
+            }
+        }
+        let _ = stop_restate.send(());
+        let _ = restate_handle.await;
     }
 
+    if let Some(provider) = tracer_provider {
+        provider.shutdown()?;
+    }
     Ok(())
 }
+
+// Re-reads the config file and env vars on SIGHUP, a bad config leaves the running one in place
+async fn reload_on_hangup(handle: ConfigHandle) {
+    let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+        tracing::warn!("SIGHUP handler unavailable, reload via PUT /admin/config only");
+        return;
+    };
+    while hangup.recv().await.is_some() {
+        let result = Cli::try_parse()
+            .map_err(anyhow::Error::from)
+            .and_then(Config::load)
+            .and_then(|next| handle.reload(next));
+        if let Err(e) = result {
+            tracing::error!(error = %format!("{e:#}"), "config reload rejected");
+        }
+    }
+}
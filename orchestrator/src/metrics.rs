@@ -0,0 +1,217 @@
+use crate::backend::ResourceUsage;
+use axum::extract::{MatchedPath, Request};
+use axum::http::{StatusCode, header::CONTENT_TYPE};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use prometheus::{
+    Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Instant;
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub active_sessions: IntGauge,
+    pub active_workers: IntGauge,
+    pub spawn_latency: Histogram,
+    pub spawn_failures: IntCounterVec,
+    pub reaper_deletions: IntCounterVec,
+    pub http_latency: HistogramVec,
+    pub http_requests: IntCounterVec,
+    pub worker_rss: IntGaugeVec,
+    pub worker_cpu: GaugeVec,
+    pub worker_fds: IntGaugeVec,
+    pub warm_pool_hits: IntCounter,
+    pub warm_pool_misses: IntCounter,
+    pub warm_pool_idle: IntGauge,
+    pub worker_retirements: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("orchestrator".to_string()), None)
+            .expect("valid metrics prefix");
+
+        let active_sessions =
+            IntGauge::new("active_sessions", "Sessions currently in the pool").unwrap();
+        let active_workers =
+            IntGauge::new("active_workers", "Workers currently in the pool").unwrap();
+        // Covers the start of spawn_worker through the session being ready
+        let spawn_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "spawn_latency_seconds",
+                "Worker spawn to session ready latency",
+            )
+            .buckets(vec![0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0]),
+        )
+        .unwrap();
+        let spawn_failures = IntCounterVec::new(
+            Opts::new("spawn_failures_total", "Failed worker spawns"),
+            &["reason"],
+        )
+        .unwrap();
+        let reaper_deletions = IntCounterVec::new(
+            Opts::new(
+                "reaper_deletions_total",
+                "Sessions and workers removed by the pollers",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+        let http_latency = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Axum request latency"),
+            &["method", "route"],
+        )
+        .unwrap();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Axum requests by status code"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        // Labelled by worker, series are dropped when the worker stops
+        let worker_rss = IntGaugeVec::new(
+            Opts::new("worker_rss_bytes", "Resident memory of each worker"),
+            &["worker_id"],
+        )
+        .unwrap();
+        let worker_cpu = GaugeVec::new(
+            Opts::new("worker_cpu_seconds", "CPU time each worker used so far"),
+            &["worker_id"],
+        )
+        .unwrap();
+        let worker_fds = IntGaugeVec::new(
+            Opts::new("worker_open_fds", "Open file descriptors of each worker"),
+            &["worker_id"],
+        )
+        .unwrap();
+        let warm_pool_hits = IntCounter::new(
+            "warm_pool_hits_total",
+            "Sessions placed on an idle warm worker",
+        )
+        .unwrap();
+        let warm_pool_misses = IntCounter::new(
+            "warm_pool_misses_total",
+            "Sessions that had to cold start a worker while the warm pool is enabled",
+        )
+        .unwrap();
+        let warm_pool_idle =
+            IntGauge::new("warm_pool_idle", "Warm workers ready for a session").unwrap();
+        let worker_retirements = IntCounterVec::new(
+            Opts::new(
+                "worker_retirements_total",
+                "Workers stopped by the recycle policy",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+
+        registry
+            .register(Box::new(active_sessions.clone()))
+            .unwrap();
+        registry.register(Box::new(active_workers.clone())).unwrap();
+        registry.register(Box::new(spawn_latency.clone())).unwrap();
+        registry.register(Box::new(spawn_failures.clone())).unwrap();
+        registry
+            .register(Box::new(reaper_deletions.clone()))
+            .unwrap();
+        registry.register(Box::new(http_latency.clone())).unwrap();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(worker_rss.clone())).unwrap();
+        registry.register(Box::new(worker_cpu.clone())).unwrap();
+        registry.register(Box::new(worker_fds.clone())).unwrap();
+        registry.register(Box::new(warm_pool_hits.clone())).unwrap();
+        registry
+            .register(Box::new(warm_pool_misses.clone()))
+            .unwrap();
+        registry.register(Box::new(warm_pool_idle.clone())).unwrap();
+        registry
+            .register(Box::new(worker_retirements.clone()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            active_sessions,
+            active_workers,
+            spawn_latency,
+            spawn_failures,
+            reaper_deletions,
+            http_latency,
+            http_requests,
+            worker_rss,
+            worker_cpu,
+            worker_fds,
+            warm_pool_hits,
+            warm_pool_misses,
+            warm_pool_idle,
+            worker_retirements,
+        }
+    }
+
+    // Gauges mirror the persisted pool every time a handler writes it back
+    pub fn observe_pool(&self, sessions: usize, workers: usize) {
+        self.active_sessions.set(sessions as i64);
+        self.active_workers.set(workers as i64);
+    }
+
+    pub fn observe_usage(&self, worker_id: &str, usage: &ResourceUsage) {
+        self.worker_rss
+            .with_label_values(&[worker_id])
+            .set(usage.rss_bytes as i64);
+        self.worker_cpu
+            .with_label_values(&[worker_id])
+            .set(usage.cpu_seconds);
+        if let Some(fds) = usage.open_fds {
+            self.worker_fds
+                .with_label_values(&[worker_id])
+                .set(fds as i64);
+        }
+    }
+
+    pub fn forget_worker(&self, worker_id: &str) {
+        let _ = self.worker_rss.remove_label_values(&[worker_id]);
+        let _ = self.worker_cpu.remove_label_values(&[worker_id]);
+        let _ = self.worker_fds.remove_label_values(&[worker_id]);
+    }
+
+    pub fn spawn_failed(&self, reason: &str) {
+        self.spawn_failures.with_label_values(&[reason]).inc();
+    }
+}
+
+pub async fn metrics() -> Response {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&METRICS.registry.gather(), &mut buffer) {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to encode metrics: {e}"),
+        )
+            .into_response();
+    }
+    ([(CONTENT_TYPE, prometheus::TEXT_FORMAT)], buffer).into_response()
+}
+
+// Records latency and status per matched route, so path ids don't explode cardinality
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    METRICS
+        .http_latency
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    METRICS
+        .http_requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    response
+}
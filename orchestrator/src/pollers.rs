@@ -0,0 +1,95 @@
+use crate::api;
+use crate::config::{Config, ConfigHandle};
+use reqwest::Client;
+use std::time::Duration;
+
+// How often a paused poller looks at the config again
+const PAUSED_RECHECK: Duration = Duration::from_secs(5);
+
+// Starts the drivers of the pool's periodic handlers
+pub fn spawn(config: &ConfigHandle) {
+    tokio::spawn(run(config.clone(), "poll_stale_workers", |c| {
+        c.poll.workers_interval()
+    }));
+    tokio::spawn(run(config.clone(), "poll_stale_sessions", |c| {
+        c.poll.sessions_interval()
+    }));
+}
+
+// Invokes a pool handler through the ingress every `interval`, read again before each pass so
+// a reload applies to the next one. A failed pass is only logged, the next one retries.
+pub async fn run(
+    config: ConfigHandle,
+    handler: &'static str,
+    interval: fn(&Config) -> Option<Duration>,
+) {
+    let client = Client::new();
+    loop {
+        let Some(delay) = interval(&config.get()) else {
+            tokio::time::sleep(PAUSED_RECHECK).await;
+            continue;
+        };
+        tokio::time::sleep(delay).await;
+        if let Err(e) = api::call_pool(&client, &config.get(), handler).await {
+            tracing::warn!(handler, error = %format!("{e:#}"), "periodic pool call failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::{Path, State};
+    use axum::routing::post;
+    use std::sync::{Arc, Mutex};
+
+    type Calls = Arc<Mutex<Vec<String>>>;
+
+    async fn record(State(calls): State<Calls>, Path(handler): Path<String>) {
+        calls.lock().unwrap().push(handler);
+    }
+
+    // Stands in for the Restate ingress, records which handlers were invoked
+    async fn start_ingress() -> (String, Calls) {
+        let calls = Calls::default();
+        let app = Router::new()
+            .route("/WorkerPoolService/pool/{handler}", post(record))
+            .with_state(calls.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}", addr), calls)
+    }
+
+    #[tokio::test]
+    async fn invokes_the_pollers_without_being_asked() {
+        let (ingress, calls) = start_ingress().await;
+        let mut config = Config::default();
+        config.server.restate_ingress_url = ingress;
+        config.poll.workers_interval_secs = 1;
+        config.poll.sessions_interval_secs = 2;
+        let handle = ConfigHandle::new(config);
+        spawn(&handle);
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        let calls = calls.lock().unwrap().clone();
+        let count = |handler: &str| calls.iter().filter(|c| *c == handler).count();
+        assert_eq!(count("poll_stale_workers"), 2, "{calls:?}");
+        assert_eq!(count("poll_stale_sessions"), 1, "{calls:?}");
+    }
+
+    #[tokio::test]
+    async fn a_zero_interval_pauses_the_poller() {
+        let (ingress, calls) = start_ingress().await;
+        let mut config = Config::default();
+        config.server.restate_ingress_url = ingress;
+        config.poll.workers_interval_secs = 0;
+        tokio::spawn(run(ConfigHandle::new(config), "poll_stale_workers", |c| {
+            c.poll.workers_interval()
+        }));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(calls.lock().unwrap().is_empty());
+    }
+}
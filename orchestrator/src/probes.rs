@@ -0,0 +1,327 @@
+use crate::config::{BackendKind, ConfigHandle};
+use crate::shutdown::LIFECYCLE;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use reqwest::{Client, Response, Url};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Restate service name the endpoint on :4000 registers
+const SERVICE_NAME: &str = "WorkerPoolService";
+
+// The parts of the admin API's service and deployment we look at
+#[derive(Deserialize)]
+struct ServiceMetadata {
+    deployment_id: String,
+}
+
+#[derive(Deserialize)]
+struct Deployment {
+    // Unset for Lambda deployments
+    uri: Option<String>,
+}
+
+pub struct Probes {
+    config: ConfigHandle,
+    client: Client,
+}
+
+#[derive(Serialize)]
+pub struct Check {
+    name: &'static str,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    ready: bool,
+    checks: Vec<Check>,
+}
+
+impl Probes {
+    pub fn new(config: ConfigHandle) -> Self {
+        // Probes must answer quickly, a hung dependency counts as down
+        let client = Client::builder()
+            .timeout(Duration::from_secs(2))
+            .build()
+            .expect("static client config");
+        Probes { config, client }
+    }
+
+    async fn get(&self, url: String) -> Result<Response, String> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(format!("responded with {}", response.status()))
+        }
+    }
+
+    async fn get_ok(&self, url: String) -> Result<(), String> {
+        self.get(url).await.map(|_| ())
+    }
+
+    async fn check_restate_ingress(&self) -> Result<(), String> {
+        let config = self.config.get();
+        self.get_ok(format!(
+            "{}/restate/health",
+            config.server.restate_ingress_url
+        ))
+        .await
+    }
+
+    // The admin API only knows the service once a deployment was registered, and that
+    // deployment has to be this endpoint on :4000 rather than some other instance
+    async fn check_service_registered(&self) -> Result<(), String> {
+        let config = self.config.get();
+        let admin_url = &config.server.restate_admin_url;
+        let service: ServiceMetadata = self
+            .get(format!("{}/services/{}", admin_url, SERVICE_NAME))
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let deployment: Deployment = self
+            .get(format!(
+                "{}/deployments/{}",
+                admin_url, service.deployment_id
+            ))
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let uri = deployment.uri.unwrap_or_default();
+        if serves(&uri, config.server.restate_listen) {
+            Ok(())
+        } else {
+            Err(format!(
+                "deployment {} is {:?}, not this endpoint on {}",
+                service.deployment_id, uri, config.server.restate_listen
+            ))
+        }
+    }
+
+    fn check_worker_binary(&self) -> Result<(), String> {
+        let config = self.config.get();
+        let binary = match config.worker.backend {
+            BackendKind::Local => &config.worker.binary,
+            BackendKind::Chromium => &config.chromium.binary,
+            // Other backends run the worker image, not a local binary
+            BackendKind::Container | BackendKind::Kubernetes | BackendKind::Mock => return Ok(()),
+        };
+        resolve_on_path(binary)
+            .map(|_| ())
+            .ok_or_else(|| format!("{} not found on PATH", binary))
+    }
+
+    pub async fn readiness(&self) -> Readiness {
+        let (ingress, registered) = tokio::join!(
+            self.check_restate_ingress(),
+            self.check_service_registered()
+        );
+        let checks: Vec<Check> = [
+            ("restate_ingress", ingress),
+            ("service_registered", registered),
+            ("worker_binary", self.check_worker_binary()),
+            // Takes a draining instance out of load balancing
+            (
+                "not_draining",
+                if LIFECYCLE.is_draining() {
+                    Err("draining".to_string())
+                } else {
+                    Ok(())
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(name, result)| Check {
+            name,
+            ok: result.is_ok(),
+            error: result.err(),
+        })
+        .collect();
+        Readiness {
+            ready: checks.iter().all(|c| c.ok),
+            checks,
+        }
+    }
+}
+
+// Whether a deployment URI reaches an endpoint listening on `listen`. Any host name may lead
+// to a wildcard address, only the port can be told apart then.
+fn serves(uri: &str, listen: SocketAddr) -> bool {
+    let Ok(uri) = Url::parse(uri) else {
+        return false;
+    };
+    if uri.port_or_known_default() != Some(listen.port()) {
+        return false;
+    }
+    if listen.ip().is_unspecified() {
+        return true;
+    }
+    match uri.host_str() {
+        Some("localhost") => listen.ip().is_loopback(),
+        Some(host) => host.trim_matches(['[', ']']).parse::<IpAddr>() == Ok(listen.ip()),
+        None => false,
+    }
+}
+
+// Same lookup the OS does when spawning `binary` without a path
+pub fn resolve_on_path(binary: &str) -> Option<std::path::PathBuf> {
+    if binary.contains('/') {
+        let path = Path::new(binary);
+        return is_executable(path).then(|| path.to_path_buf());
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+// Liveness, answering at all means the Axum server is up
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+pub async fn readyz(State(probes): State<Arc<Probes>>) -> (StatusCode, Json<Readiness>) {
+    let readiness = probes.readiness().await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::Router;
+    use axum::extract::Path as UrlPath;
+    use axum::routing::get;
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn deployment_uris_must_reach_the_listen_address() {
+        let loopback: SocketAddr = ([127, 0, 0, 1], 4000).into();
+        assert!(serves("http://127.0.0.1:4000/", loopback));
+        assert!(serves("http://localhost:4000", loopback));
+        assert!(!serves("http://127.0.0.1:4001/", loopback));
+        assert!(!serves("http://10.0.0.7:4000/", loopback));
+        assert!(!serves("http://orchestrator:4000/", loopback));
+        // Port 80 unless given
+        assert!(!serves("http://127.0.0.1/", loopback));
+        assert!(!serves("", loopback));
+
+        let wildcard: SocketAddr = ([0, 0, 0, 0], 4000).into();
+        assert!(serves("http://orchestrator:4000/", wildcard));
+        assert!(!serves("http://orchestrator:9080/", wildcard));
+        assert!(serves("http://[::1]:4000/", "[::1]:4000".parse().unwrap()));
+    }
+
+    // Stands in for the Restate ingress and admin API, with `WorkerPoolService` registered by
+    // the deployment at `uri` unless that is None
+    async fn start_restate(uri: Option<&str>) -> Probes {
+        let service = uri.map(|_| json!({ "name": SERVICE_NAME, "deployment_id": "dp_1" }));
+        let deployment = json!({ "id": "dp_1", "uri": uri });
+        let app = Router::new()
+            .route("/restate/health", get(|| async { "" }))
+            .route(
+                "/services/{name}",
+                get(move |UrlPath(name): UrlPath<String>| async move {
+                    match &service {
+                        Some(service) if name == SERVICE_NAME => Ok(Json(service.clone())),
+                        _ => Err(StatusCode::NOT_FOUND),
+                    }
+                }),
+            )
+            .route(
+                "/deployments/{id}",
+                get(move |UrlPath(id): UrlPath<String>| async move {
+                    if id == "dp_1" {
+                        Ok(Json::<Value>(deployment.clone()))
+                    } else {
+                        Err(StatusCode::NOT_FOUND)
+                    }
+                }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = Config::default();
+        config.server.restate_ingress_url = format!("http://{}", addr);
+        config.server.restate_admin_url = format!("http://{}", addr);
+        config.server.restate_listen = ([127, 0, 0, 1], 4000).into();
+        config.worker.backend = BackendKind::Mock;
+        Probes::new(ConfigHandle::new(config))
+    }
+
+    fn failed(readiness: &Readiness) -> Vec<&str> {
+        readiness
+            .checks
+            .iter()
+            .filter(|c| !c.ok)
+            .map(|c| c.name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn ready_once_this_endpoint_is_registered() {
+        let probes = start_restate(Some("http://127.0.0.1:4000/")).await;
+        let readiness = probes.readiness().await;
+        assert!(readiness.ready, "{:?}", failed(&readiness));
+    }
+
+    #[tokio::test]
+    async fn another_deployment_of_the_service_is_not_enough() {
+        let probes = start_restate(Some("http://127.0.0.1:4001/")).await;
+        let error = probes.check_service_registered().await.unwrap_err();
+        assert!(error.contains("http://127.0.0.1:4001/"), "{error}");
+        let readiness = probes.readiness().await;
+        assert!(!readiness.ready);
+        assert_eq!(failed(&readiness), ["service_registered"]);
+    }
+
+    #[tokio::test]
+    async fn unregistered_service_is_not_ready() {
+        let probes = start_restate(None).await;
+        let error = probes.check_service_registered().await.unwrap_err();
+        assert!(error.contains("404"), "{error}");
+        assert!(probes.check_restate_ingress().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unreachable_restate_fails_both_checks() {
+        let mut config = Config::default();
+        // Nothing listens on the discard port
+        config.server.restate_ingress_url = "http://127.0.0.1:9".to_string();
+        config.server.restate_admin_url = "http://127.0.0.1:9".to_string();
+        config.worker.backend = BackendKind::Mock;
+        let readiness = Probes::new(ConfigHandle::new(config)).readiness().await;
+        assert_eq!(
+            failed(&readiness),
+            ["restate_ingress", "service_registered"]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use crate::api;
+use crate::config::ConfigHandle;
+use reqwest::Client;
+use std::time::Duration;
+
+// Retried until the first pass succeeds, the Restate server may still be starting
+const STARTUP_RETRY: Duration = Duration::from_secs(5);
+
+// Invokes the pool's reconcile handler through the ingress once at startup, then every
+// `reconcile.interval_secs` while that is non zero
+pub async fn run(config: ConfigHandle) {
+    let client = Client::new();
+    let mut reconciled = false;
+    loop {
+        let current = config.get();
+        let delay = match api::call_pool(&client, &current, "reconcile").await {
+            Ok(report) => {
+                tracing::info!(%report, "reconciled pool state");
+                reconciled = true;
+                match current.reconcile.interval() {
+                    Some(interval) => interval,
+                    None => return,
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = %format!("{e:#}"), "reconciliation failed");
+                match current.reconcile.interval() {
+                    Some(interval) if reconciled => interval,
+                    _ => STARTUP_RETRY,
+                }
+            }
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
@@ -0,0 +1,409 @@
+use crate::config::Strategy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+// Memory and CPU, what a session requests or a worker offers. Unset is not accounted.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Resources {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<f64>,
+}
+
+// More than any worker could offer, a request above it is a mistake
+const MAX_MEMORY_BYTES: u64 = 1 << 50;
+
+impl Resources {
+    // Requests come from clients, sizes that can't be met or summed are refused up front
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(memory) = self.memory_bytes
+            && !(1..=MAX_MEMORY_BYTES).contains(&memory)
+        {
+            return Err(format!(
+                "memory_bytes must be between 1 and {}",
+                MAX_MEMORY_BYTES
+            ));
+        }
+        if let Some(cpus) = self.cpus
+            && !(cpus.is_finite() && cpus >= 0.0)
+        {
+            return Err("cpus must be a finite number, not negative".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, other: &Resources) {
+        if let Some(memory) = other.memory_bytes {
+            let total = self.memory_bytes.get_or_insert(0);
+            *total = total.saturating_add(memory);
+        }
+        if let Some(cpus) = other.cpus {
+            *self.cpus.get_or_insert(0.0) += cpus;
+        }
+    }
+}
+
+// The constraints a new session places on its worker
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema, ToSchema)]
+pub struct PlacementRequest {
+    // Every one must be on the worker with the same value
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub resources: Resources,
+}
+
+// What the scheduler sees of a worker
+pub struct Candidate<'a> {
+    pub worker_id: &'a str,
+    // Unset for backends that don't spread workers over nodes, those count as one node
+    pub node: Option<&'a str>,
+    pub labels: &'a BTreeMap<String, String>,
+    // False while it warms up, retires or has served its last session under the recycle policy
+    pub accepting: bool,
+    pub sessions: u64,
+    pub capacity: u64,
+    pub offered: &'a Resources,
+    // Summed over its sessions
+    pub requested: Resources,
+}
+
+impl Candidate<'_> {
+    fn load(&self) -> f64 {
+        self.sessions as f64 / self.capacity.max(1) as f64
+    }
+
+    // Why the session can't go here, None when it fits
+    fn misfit(&self, request: &PlacementRequest) -> Option<&'static str> {
+        if !self.accepting {
+            return Some("not accepting");
+        }
+        if self.sessions >= self.capacity.max(1) {
+            return Some("full");
+        }
+        if request
+            .labels
+            .iter()
+            .any(|(key, value)| self.labels.get(key) != Some(value))
+        {
+            return Some("labels");
+        }
+        let memory = match (self.offered.memory_bytes, request.resources.memory_bytes) {
+            (Some(offered), Some(wanted)) => {
+                self.requested
+                    .memory_bytes
+                    .unwrap_or(0)
+                    .saturating_add(wanted)
+                    <= offered
+            }
+            _ => true,
+        };
+        let cpus = match (self.offered.cpus, request.resources.cpus) {
+            (Some(offered), Some(wanted)) => self.requested.cpus.unwrap_or(0.0) + wanted <= offered,
+            _ => true,
+        };
+        if !memory || !cpus {
+            return Some("resources");
+        }
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Placement {
+    // Index into the candidates
+    Worker(usize),
+    Start,
+    // Not even a fresh worker would fit
+    Unschedulable,
+}
+
+#[derive(Debug)]
+pub struct Decision {
+    pub placement: Placement,
+    // Recorded in the session's history
+    pub reason: String,
+}
+
+// Picks an existing worker for the session and only asks for a new one when none fits. `fresh`
+// describes the worker that would be started.
+pub fn place(
+    strategy: Strategy,
+    candidates: &[Candidate],
+    request: &PlacementRequest,
+    fresh: &Candidate,
+) -> Decision {
+    let fitting: Vec<usize> = (0..candidates.len())
+        .filter(|&i| candidates[i].misfit(request).is_none())
+        .collect();
+
+    // Sessions per node over every worker, not only the fitting ones
+    let mut node_sessions: BTreeMap<Option<&str>, u64> = BTreeMap::new();
+    for candidate in candidates {
+        *node_sessions.entry(candidate.node).or_default() += candidate.sessions;
+    }
+
+    // Lower is better, ties go to the earlier worker
+    let score = |c: &Candidate| -> (f64, f64) {
+        match strategy {
+            Strategy::Binpack => (-c.load(), 0.0),
+            Strategy::LeastLoaded => (c.load(), 0.0),
+            Strategy::Spread => (node_sessions[&c.node] as f64, c.load()),
+        }
+    };
+    let mut best: Option<usize> = None;
+    for &i in &fitting {
+        if best.is_none_or(|b| score(&candidates[i]) < score(&candidates[b])) {
+            best = Some(i);
+        }
+    }
+
+    if let Some(index) = best {
+        let chosen = &candidates[index];
+        return Decision {
+            placement: Placement::Worker(index),
+            reason: format!(
+                "{}: worker {} on node {} at {}/{} sessions, {} of {} workers fit",
+                strategy.as_str(),
+                chosen.worker_id,
+                chosen.node.unwrap_or("-"),
+                chosen.sessions,
+                chosen.capacity.max(1),
+                fitting.len(),
+                candidates.len(),
+            ),
+        };
+    }
+    match fresh.misfit(request) {
+        None => Decision {
+            placement: Placement::Start,
+            reason: format!(
+                "{}: none of {} workers fit, starting one",
+                strategy.as_str(),
+                candidates.len()
+            ),
+        },
+        Some(misfit) => Decision {
+            placement: Placement::Unschedulable,
+            reason: format!("No worker can satisfy the session's {}", misfit),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static NO_LABELS: BTreeMap<String, String> = BTreeMap::new();
+    static UNLIMITED: Resources = Resources {
+        memory_bytes: None,
+        cpus: None,
+    };
+
+    fn worker<'a>(id: &'a str, node: Option<&'a str>, sessions: u64) -> Candidate<'a> {
+        Candidate {
+            worker_id: id,
+            node,
+            labels: &NO_LABELS,
+            accepting: true,
+            sessions,
+            capacity: 4,
+            offered: &UNLIMITED,
+            requested: Resources::default(),
+        }
+    }
+
+    fn chosen(strategy: Strategy, candidates: &[Candidate]) -> Placement {
+        place(
+            strategy,
+            candidates,
+            &PlacementRequest::default(),
+            &worker("", None, 0),
+        )
+        .placement
+    }
+
+    #[test]
+    fn binpack_fills_the_busiest_worker() {
+        let candidates = [
+            worker("a", None, 1),
+            worker("b", None, 3),
+            worker("c", None, 2),
+        ];
+        assert_eq!(chosen(Strategy::Binpack, &candidates), Placement::Worker(1));
+    }
+
+    #[test]
+    fn least_loaded_picks_the_idlest_worker() {
+        let candidates = [
+            worker("a", None, 2),
+            worker("b", None, 3),
+            worker("c", None, 1),
+        ];
+        assert_eq!(
+            chosen(Strategy::LeastLoaded, &candidates),
+            Placement::Worker(2)
+        );
+
+        // Load is relative to capacity
+        // More sessions, but a smaller share of its capacity
+        let mut big = worker("d", None, 3);
+        big.capacity = 16;
+        let candidates = [worker("a", None, 1), big];
+        assert_eq!(
+            chosen(Strategy::LeastLoaded, &candidates),
+            Placement::Worker(1)
+        );
+    }
+
+    #[test]
+    fn spread_picks_the_quietest_node_then_the_idlest_worker() {
+        let candidates = [
+            worker("a", Some("n1"), 0),
+            worker("b", Some("n1"), 3),
+            worker("c", Some("n2"), 2),
+            worker("d", Some("n2"), 1),
+        ];
+        // n1 carries 3 sessions, n2 carries 3 too, so the tie falls to the idler worker
+        assert_eq!(chosen(Strategy::Spread, &candidates), Placement::Worker(0));
+
+        let candidates = [
+            worker("a", Some("n1"), 1),
+            worker("b", Some("n1"), 2),
+            worker("c", Some("n2"), 2),
+        ];
+        assert_eq!(chosen(Strategy::Spread, &candidates), Placement::Worker(2));
+    }
+
+    #[test]
+    fn ties_go_to_the_earlier_worker() {
+        let candidates = [worker("a", None, 1), worker("b", None, 1)];
+        for strategy in [Strategy::Binpack, Strategy::LeastLoaded, Strategy::Spread] {
+            assert_eq!(chosen(strategy, &candidates), Placement::Worker(0));
+        }
+    }
+
+    #[test]
+    fn skips_workers_that_do_not_fit() {
+        let labels = BTreeMap::from([("region".to_string(), "eu".to_string())]);
+        let offered = Resources {
+            memory_bytes: Some(1024),
+            cpus: Some(2.0),
+        };
+        let request = PlacementRequest {
+            labels: labels.clone(),
+            resources: Resources {
+                memory_bytes: Some(512),
+                cpus: Some(1.0),
+            },
+        };
+        let fitting = || Candidate {
+            labels: &labels,
+            offered: &offered,
+            ..worker("ok", None, 0)
+        };
+
+        let full = Candidate {
+            sessions: 4,
+            ..fitting()
+        };
+        let not_accepting = Candidate {
+            accepting: false,
+            ..fitting()
+        };
+        let other_labels = Candidate {
+            labels: &NO_LABELS,
+            ..fitting()
+        };
+        let no_memory_left = Candidate {
+            requested: Resources {
+                memory_bytes: Some(768),
+                cpus: None,
+            },
+            ..fitting()
+        };
+        let candidates = [full, not_accepting, other_labels, no_memory_left, fitting()];
+        let decision = place(Strategy::Binpack, &candidates, &request, &fitting());
+        assert_eq!(decision.placement, Placement::Worker(4));
+        assert!(
+            decision.reason.contains("1 of 5 workers fit"),
+            "{}",
+            decision.reason
+        );
+
+        // Without the fitting one a fresh worker is started
+        let decision = place(Strategy::Binpack, &candidates[..4], &request, &fitting());
+        assert_eq!(decision.placement, Placement::Start);
+    }
+
+    #[test]
+    fn oversized_requests_do_not_wrap_around() {
+        let offered = Resources {
+            memory_bytes: Some(1024),
+            cpus: None,
+        };
+        let busy = Candidate {
+            offered: &offered,
+            requested: Resources {
+                memory_bytes: Some(512),
+                cpus: None,
+            },
+            ..worker("a", None, 0)
+        };
+        let request = PlacementRequest {
+            resources: Resources {
+                memory_bytes: Some(u64::MAX),
+                cpus: None,
+            },
+            ..Default::default()
+        };
+        assert_eq!(busy.misfit(&request), Some("resources"));
+
+        let mut total = busy.requested.clone();
+        total.add(&request.resources);
+        assert_eq!(total.memory_bytes, Some(u64::MAX));
+    }
+
+    #[test]
+    fn requests_with_impossible_sizes_are_refused() {
+        let resources = |memory_bytes, cpus| Resources { memory_bytes, cpus };
+        assert!(resources(None, None).validate().is_ok());
+        assert!(resources(Some(1 << 30), Some(0.5)).validate().is_ok());
+        assert!(resources(None, Some(0.0)).validate().is_ok());
+        for invalid in [
+            resources(Some(0), None),
+            resources(Some(u64::MAX), None),
+            resources(None, Some(-1.0)),
+            resources(None, Some(f64::NAN)),
+            resources(None, Some(f64::INFINITY)),
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn unschedulable_when_not_even_a_fresh_worker_fits() {
+        let request = PlacementRequest {
+            resources: Resources {
+                memory_bytes: Some(4096),
+                cpus: None,
+            },
+            ..Default::default()
+        };
+        let offered = Resources {
+            memory_bytes: Some(1024),
+            cpus: None,
+        };
+        let fresh = Candidate {
+            offered: &offered,
+            ..worker("", None, 0)
+        };
+        let decision = place(Strategy::LeastLoaded, &[], &request, &fresh);
+        assert_eq!(decision.placement, Placement::Unschedulable);
+        assert!(decision.reason.contains("resources"), "{}", decision.reason);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::backend::WorkerBackend;
+use crate::config::Config;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::watch;
+
+pub static LIFECYCLE: LazyLock<Lifecycle> = LazyLock::new(Lifecycle::new);
+
+// Draining stops new placements while existing sessions keep being served. Shutting down
+// drains, then stops both servers once their in-flight requests finished.
+pub struct Lifecycle {
+    draining: AtomicBool,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Lifecycle {
+    pub(crate) fn new() -> Self {
+        Lifecycle {
+            draining: AtomicBool::new(false),
+            shutdown: watch::channel(false).0,
+        }
+    }
+
+    // True when this call started the drain
+    pub fn drain(&self) -> bool {
+        !self.draining.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn shut_down(&self) {
+        self.drain();
+        self.shutdown.send_replace(true);
+    }
+
+    pub async fn shutting_down(&self) {
+        let mut shutdown = self.shutdown.subscribe();
+        let _ = shutdown.wait_for(|s| *s).await;
+    }
+}
+
+// Starts the shutdown on the first SIGTERM or SIGINT
+pub async fn on_signal() {
+    let (Ok(mut term), Ok(mut int)) = (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    ) else {
+        tracing::warn!("signal handlers unavailable, shutdown won't drain");
+        return;
+    };
+    tokio::select! {
+        _ = term.recv() => tracing::info!("SIGTERM received, shutting down"),
+        _ = int.recv() => tracing::info!("SIGINT received, shutting down"),
+    }
+    LIFECYCLE.shut_down();
+}
+
+// Stops every worker through the pool so its state and session histories follow. When the
+// Restate server can't be reached, stops whatever the backend finds running instead.
+pub async fn terminate_workers(config: &Config, backend: &dyn WorkerBackend) {
+    let client = reqwest::Client::new();
+    match crate::api::call_pool(&client, config, "terminate_workers").await {
+        Ok(stopped) => {
+            tracing::info!(%stopped, "terminated workers");
+            return;
+        }
+        Err(e) => tracing::warn!(
+            error = %format!("{e:#}"),
+            "terminating through the pool failed, stopping workers directly"
+        ),
+    }
+    let workers = match backend.discover().await {
+        Ok(workers) => workers,
+        Err(e) => {
+            tracing::error!(error = %format!("{e:#}"), "failed to discover workers");
+            return;
+        }
+    };
+    for worker in workers {
+        if let Err(e) = backend.stop(&worker).await {
+            tracing::warn!(worker_id = %worker.id, error = %format!("{e:#}"), "failed to stop worker");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn only_the_first_drain_starts_it() {
+        let lifecycle = Lifecycle::new();
+        assert!(!lifecycle.is_draining());
+        assert!(lifecycle.drain());
+        assert!(!lifecycle.drain());
+        assert!(lifecycle.is_draining());
+    }
+
+    #[tokio::test]
+    async fn shutting_down_drains_and_wakes_waiters() {
+        let lifecycle = Lifecycle::new();
+        let waiting = tokio::time::timeout(Duration::from_millis(50), lifecycle.shutting_down());
+        assert!(waiting.await.is_err());
+
+        // Waiters from before and after the shutdown both return
+        let waited = tokio::time::timeout(Duration::from_secs(1), async {
+            tokio::join!(lifecycle.shutting_down(), async { lifecycle.shut_down() });
+            lifecycle.shutting_down().await;
+        });
+        waited.await.unwrap();
+        assert!(lifecycle.is_draining());
+        assert!(!lifecycle.drain());
+    }
+}
@@ -0,0 +1,358 @@
+use axum::extract::{FromRequestParts, MatchedPath, Request};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, request::Parts};
+use axum::middleware::Next;
+use axum::response::Response;
+use opentelemetry::global;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use restate_sdk::context::HeaderMap as RestateHeaderMap;
+use restate_sdk::filter::ReplayAwareFilter;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::time::Instant;
+use tracing::{Instrument, field};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+// Generated at the Axum layer and forwarded to Restate so one session can be grepped end to end
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+// Set by the Restate ingress on responses
+pub const INVOCATION_ID_HEADER: &str = "x-restate-id";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    #[default]
+    Pretty,
+}
+
+impl LogFormat {
+    pub fn parse(raw: &str) -> Option<LogFormat> {
+        match raw {
+            "json" => Some(LogFormat::Json),
+            "pretty" => Some(LogFormat::Pretty),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OtlpProtocol {
+    #[default]
+    #[serde(rename = "grpc")]
+    Grpc,
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+}
+
+impl OtlpProtocol {
+    // Same values as OTEL_EXPORTER_OTLP_PROTOCOL
+    pub fn parse(raw: &str) -> Option<OtlpProtocol> {
+        match raw {
+            "grpc" => Some(OtlpProtocol::Grpc),
+            "http/protobuf" => Some(OtlpProtocol::HttpProtobuf),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OtlpConfig {
+    pub endpoint: String,
+    pub protocol: OtlpProtocol,
+}
+
+fn tracer_provider(otlp: &OtlpConfig) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = match otlp.protocol {
+        OtlpProtocol::Grpc => opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(&otlp.endpoint)
+            .build()?,
+        OtlpProtocol::HttpProtobuf => opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&otlp.endpoint)
+            .build()?,
+    };
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name("browser-orchestrator")
+                .build(),
+        )
+        .build())
+}
+
+// Without context activation a span only starts when it ends or a child or `inject_context`
+// needs it, so the Restate handlers can still take their parent from the ingress headers after
+// `#[instrument]` entered their span
+fn otel_layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("browser-orchestrator"))
+        .with_context_activation(false)
+}
+
+// Filtering follows RUST_LOG, events emitted while Restate replays a journal are dropped.
+// The returned provider must be shut down on exit to flush pending spans.
+pub fn init(
+    format: LogFormat,
+    otlp: Option<&OtlpConfig>,
+) -> anyhow::Result<Option<SdkTracerProvider>> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = otlp.map(tracer_provider).transpose()?;
+    let otel = provider.as_ref().map(otel_layer);
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry().with(filter).with(otel);
+    match format {
+        LogFormat::Json => registry
+            .with(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_filter(ReplayAwareFilter),
+            )
+            .init(),
+        LogFormat::Pretty => registry
+            .with(fmt::layer().pretty().with_filter(ReplayAwareFilter))
+            .init(),
+    }
+    Ok(provider)
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct RestateHeaderExtractor<'a>(&'a RestateHeaderMap);
+
+impl Extractor for RestateHeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+// Writes the W3C traceparent of `span` into outgoing request headers
+pub fn inject_context(span: &tracing::Span, headers: &mut HeaderMap) {
+    let cx = span.context();
+    global::get_text_map_propagator(|p| p.inject_context(&cx, &mut HeaderInjector(headers)));
+}
+
+// Continues the trace started by the Axum handler that called the Restate ingress
+pub fn continue_restate_trace(headers: &RestateHeaderMap) {
+    let cx = global::get_text_map_propagator(|p| p.extract(&RestateHeaderExtractor(headers)));
+    let _ = tracing::Span::current().set_parent(cx);
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(|| RequestId(uuid::Uuid::new_v4().to_string())))
+    }
+}
+
+// Reuses the caller's request id when present, otherwise generates one
+pub async fn trace_http(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        route,
+        request_id,
+        status = field::Empty,
+    );
+    let parent =
+        global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(request.headers())));
+    let _ = span.set_parent(parent);
+    request
+        .extensions_mut()
+        .insert(RequestId(request_id.clone()));
+
+    let start = Instant::now();
+    let mut response = next.run(request).instrument(span.clone()).await;
+    span.record("status", response.status().as_u16());
+    span.in_scope(|| {
+        tracing::info!(
+            latency_ms = start.elapsed().as_millis() as u64,
+            "request completed"
+        )
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+// Ties the Axum handler span to the Restate invocation that served it
+pub fn record_invocation(response: &reqwest::Response) {
+    if let Some(id) = response
+        .headers()
+        .get(INVOCATION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        tracing::Span::current().record("invocation_id", id);
+    }
+}
+
+pub fn restate_request_id(headers: &RestateHeaderMap) -> &str {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .map(String::as_str)
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::{Body, Bytes};
+    use axum::routing::{get, post};
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use prost::Message;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    // (trace id, span name) of every span the collector received
+    type Received = Arc<Mutex<Vec<(Vec<u8>, String)>>>;
+
+    // Stands in for an OTLP/HTTP collector
+    async fn start_collector() -> (String, Received) {
+        let received = Received::default();
+        let sink = received.clone();
+        let app = Router::new().route(
+            "/v1/traces",
+            post(move |body: Bytes| async move {
+                let request = ExportTraceServiceRequest::decode(body).unwrap();
+                let spans = request
+                    .resource_spans
+                    .into_iter()
+                    .flat_map(|r| r.scope_spans)
+                    .flat_map(|s| s.spans);
+                sink.lock()
+                    .unwrap()
+                    .extend(spans.map(|s| (s.trace_id, s.name)));
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}/v1/traces", addr), received)
+    }
+
+    // What the pool handler does with the headers the Axum layer forwarded
+    async fn restate_handler(headers: RestateHeaderMap) {
+        continue_restate_trace(&headers);
+        tracing::info_span!("side_effect", op = "start_worker").in_scope(|| {});
+    }
+
+    // The Axum handler calling the ingress, the Restate hop is cut short to its headers
+    async fn handler() {
+        let mut headers = HeaderMap::new();
+        inject_context(&tracing::Span::current(), &mut headers);
+        let forwarded: RestateHeaderMap = headers
+            .iter()
+            .map(|(k, v)| (k.clone(), v.to_str().unwrap().to_string()))
+            .collect();
+        // A root span, as in the separate invocation Restate makes
+        restate_handler(forwarded)
+            .instrument(tracing::info_span!(parent: None, "spawn_worker"))
+            .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn one_trace_spans_axum_and_restate() {
+        let (endpoint, received) = start_collector().await;
+        let provider = tracer_provider(&OtlpConfig {
+            endpoint,
+            protocol: OtlpProtocol::HttpProtobuf,
+        })
+        .unwrap();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let subscriber = tracing_subscriber::registry().with(otel_layer(&provider));
+        // Spans on this thread only, the request below is served inline
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route("/session", get(handler))
+            .layer(axum::middleware::from_fn(trace_http));
+        let request = axum::http::Request::get("/session")
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+        drop(guard);
+
+        // The exporter posts from its own thread
+        let flushed = provider.clone();
+        tokio::task::spawn_blocking(move || flushed.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+        let spans = received.lock().unwrap().clone();
+        let names: Vec<&str> = spans.iter().map(|(_, name)| name.as_str()).collect();
+        for name in ["http_request", "spawn_worker", "side_effect"] {
+            assert!(names.contains(&name), "{names:?}");
+        }
+        let trace_id = &spans[0].0;
+        assert!(spans.iter().all(|(id, _)| id == trace_id), "{spans:?}");
+        let _ = tokio::time::timeout(
+            Duration::from_secs(5),
+            tokio::task::spawn_blocking(move || provider.shutdown()),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,584 @@
+// End to end tests through `api::router`, a local restate-server and the mock worker
+// backend. They need a restate-server binary, so they only run when asked for with
+// `cargo test -- --ignored`. Set RESTATE_SERVER_BIN or put restate-server on PATH; a missing
+// binary fails them instead of passing without running anything.
+use browser_orchestrator::api::{self, CreateSessionResponse, WorkerPoolService};
+use browser_orchestrator::auth::Authenticator;
+use browser_orchestrator::backend::MockBackend;
+use browser_orchestrator::config::{BackendKind, Config, ConfigHandle, Strategy};
+use browser_orchestrator::pollers;
+use browser_orchestrator::probes::resolve_on_path;
+use reqwest::{Client, StatusCode};
+use restate_sdk::prelude::*;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+struct Harness {
+    // Killed on drop
+    _restate: Child,
+    base_dir: PathBuf,
+    ingress_url: String,
+    api_url: String,
+    client: Client,
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.base_dir);
+    }
+}
+
+fn restate_server() -> PathBuf {
+    match std::env::var_os("RESTATE_SERVER_BIN") {
+        Some(path) => PathBuf::from(path),
+        None => resolve_on_path("restate-server")
+            .expect("restate-server not found, set RESTATE_SERVER_BIN or put it on PATH"),
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+async fn wait_for(client: &Client, url: &str) {
+    for _ in 0..150 {
+        if let Ok(response) = client.get(url).send().await
+            && response.status().is_success()
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    panic!("{url} never became available");
+}
+
+impl Harness {
+    async fn start() -> Harness {
+        Harness::start_with(|_| {}).await
+    }
+
+    async fn start_with(configure: impl FnOnce(&mut Config)) -> Harness {
+        let binary = restate_server();
+        let base_dir = std::env::temp_dir().join(format!("restate-{}", uuid::Uuid::new_v4()));
+        let (ingress, admin) = (free_addr(), free_addr());
+        let restate = Command::new(binary)
+            .env("RESTATE_BASE_DIR", &base_dir)
+            .env("RESTATE_INGRESS__BIND_ADDRESS", ingress.to_string())
+            .env("RESTATE_ADMIN__BIND_ADDRESS", admin.to_string())
+            .env("RESTATE_BIND_ADDRESS", free_addr().to_string())
+            .env(
+                "RESTATE_ADMIN__QUERY_ENGINE__PGSQL_BIND_ADDRESS",
+                free_addr().to_string(),
+            )
+            .kill_on_drop(true)
+            .spawn()
+            .expect("failed to start restate-server");
+
+        let mut config = Config::default();
+        config.server.listen = free_addr();
+        config.server.restate_listen = free_addr();
+        config.server.restate_ingress_url = format!("http://{}", ingress);
+        config.server.restate_admin_url = format!("http://{}", admin);
+        config.worker.backend = BackendKind::Mock;
+        config.worker.startup_delay_ms = 0;
+        // Tests invoke the periodic handlers themselves unless they turn the pollers on
+        config.poll.workers_interval_secs = 0;
+        config.poll.sessions_interval_secs = 0;
+        configure(&mut config);
+        let handle = ConfigHandle::new(config.clone());
+        pollers::spawn(&handle);
+
+        let backend = Arc::new(MockBackend::new(handle.clone()));
+        let endpoint = Endpoint::builder()
+            .bind(api::WorkerPool::new(handle.clone(), backend).serve())
+            .build();
+        let restate_listen = config.server.restate_listen;
+        tokio::spawn(async move {
+            HttpServer::new(endpoint)
+                .listen_and_serve(restate_listen)
+                .await
+        });
+
+        let listener = tokio::net::TcpListener::bind(config.server.listen)
+            .await
+            .unwrap();
+        let router = api::router(handle, Arc::new(Authenticator::new(vec![], None)));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = Client::new();
+        let admin_url = config.server.restate_admin_url.clone();
+        wait_for(&client, &format!("{}/health", admin_url)).await;
+        let registered = client
+            .post(format!("{}/deployments", admin_url))
+            .json(&json!({ "uri": format!("http://{}", restate_listen), "force": true }))
+            .send()
+            .await
+            .unwrap();
+        assert!(registered.status().is_success(), "{:?}", registered);
+        wait_for(
+            &client,
+            &format!("{}/services/WorkerPoolService", admin_url),
+        )
+        .await;
+
+        Harness {
+            _restate: restate,
+            base_dir,
+            ingress_url: config.server.restate_ingress_url,
+            api_url: format!("http://{}", config.server.listen),
+            client,
+        }
+    }
+
+    async fn create_session(&self, user: &str) -> String {
+        let response = self
+            .client
+            .post(format!("{}/session", self.api_url))
+            .json(&json!({ "user": user }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let session: CreateSessionResponse = response.json().await.unwrap();
+        let session = serde_json::to_value(session).unwrap();
+        assert_eq!(session["data"]["user"], user);
+        session["id"].as_str().unwrap().to_string()
+    }
+
+    async fn get(&self, path: &str) -> (StatusCode, String) {
+        let response = self
+            .client
+            .get(format!("{}{}", self.api_url, path))
+            .send()
+            .await
+            .unwrap();
+        (response.status(), response.text().await.unwrap())
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn session_lifecycle_end_to_end() {
+    let harness = Harness::start().await;
+    let id = harness.create_session("alice").await;
+
+    let (status, body) = harness.get(&format!("/session/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let session: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(session["id"], id.as_str());
+
+    let (status, body) = harness.get(&format!("/health/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("ok"), "{body}");
+
+    let (status, body) = harness.get(&format!("/status/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let session_status: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(session_status["available"], true);
+
+    let (status, body) = harness.get("/get_all_sessions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(&id), "{body}");
+
+    let deleted = harness
+        .client
+        .delete(format!("{}/session/{}", harness.api_url, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::OK);
+
+    // The Go tester takes any error status as the session being gone
+    let (status, body) = harness.get(&format!("/session/{}", id)).await;
+    assert_ne!(status, StatusCode::OK, "{body}");
+    assert!(
+        body.contains("Error fetching session from session_list"),
+        "{body}"
+    );
+
+    // Deleting it again changes nothing
+    harness
+        .client
+        .delete(format!("{}/session/{}", harness.api_url, id))
+        .send()
+        .await
+        .unwrap();
+    let (_, history) = harness.get(&format!("/session/{}/history", id)).await;
+    assert_eq!(history.matches("session_deleted").count(), 1, "{history}");
+    assert_eq!(history.matches("session_keepalive").count(), 1, "{history}");
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn history_is_pruned_after_retention() {
+    let harness = Harness::start_with(|config| config.session.history_retention_secs = 1).await;
+    let id = harness.create_session("judy").await;
+    let deleted = harness
+        .client
+        .delete(format!("{}/session/{}", harness.api_url, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::OK);
+    let (status, history) = harness.get(&format!("/session/{}/history", id)).await;
+    assert_eq!(status, StatusCode::OK, "{history}");
+    assert!(history.contains("session_deleted"), "{history}");
+
+    for _ in 0..50 {
+        let (status, _) = harness.get(&format!("/session/{}/history", id)).await;
+        if status == StatusCode::NOT_FOUND {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("history outlived its retention");
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn expired_sessions_are_reaped() {
+    let harness = Harness::start_with(|config| config.session.ttl_secs = 2).await;
+    let id = harness.create_session("bob").await;
+    let reap = async || {
+        let reaped = harness
+            .client
+            .post(format!(
+                "{}/WorkerPoolService/pool/poll_stale_sessions",
+                harness.ingress_url
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert!(reaped.status().is_success(), "{:?}", reaped);
+    };
+
+    // Younger than its TTL, a pass leaves it alone
+    reap().await;
+    let (status, body) = harness.get(&format!("/session/{}", id)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    reap().await;
+    let (_, body) = harness.get(&format!("/session/{}", id)).await;
+    assert!(
+        body.contains("Error fetching session from session_list"),
+        "{body}"
+    );
+    let (_, history) = harness.get(&format!("/session/{}/history", id)).await;
+    assert!(history.contains("session_expired"), "{history}");
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn reconcile_fails_sessions_of_lost_workers() {
+    let harness = Harness::start_with(|config| config.mock.crash_after_ms = Some(1000)).await;
+    let id = harness.create_session("carol").await;
+    let other = harness.create_session("carol").await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    // Listed from the pool's record while their workers don't answer
+    let (status, body) = harness.get("/get_all_sessions").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let listed: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 2, "{body}");
+    assert!(
+        listed[0]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Status unknown")
+    );
+
+    let report: Value = harness
+        .client
+        .post(format!(
+            "{}/WorkerPoolService/pool/reconcile",
+            harness.ingress_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["dead_workers"], 2);
+    assert_eq!(report["failed_sessions"], 2);
+
+    let (_, body) = harness.get(&format!("/session/{}", id)).await;
+    assert!(body.contains("Session failed: worker_lost"), "{body}");
+    let (_, history) = harness.get(&format!("/session/{}/history", id)).await;
+    assert!(history.contains("session_failed"), "{history}");
+
+    // Without a worker to ask, deleting just drops the record
+    let deleted = harness
+        .client
+        .delete(format!("{}/session/{}", harness.api_url, other))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::OK);
+    let (_, history) = harness.get(&format!("/session/{}/history", other)).await;
+    assert!(history.contains("session_deleted"), "{history}");
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn sessions_of_unhealthy_workers_fail() {
+    let harness = Harness::start_with(|config| config.mock.crash_after_ms = Some(1000)).await;
+    let id = harness.create_session("carol").await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    // The mock backend can't tell why its workers stopped
+    let polled = harness
+        .client
+        .post(format!(
+            "{}/WorkerPoolService/pool/poll_stale_workers",
+            harness.ingress_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert!(polled.status().is_success(), "{:?}", polled);
+    let (_, body) = harness.get(&format!("/session/{}", id)).await;
+    assert!(
+        body.contains("Session failed: health_check_failed"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn workers_over_their_memory_budget_are_killed() {
+    let harness = Harness::start_with(|config| {
+        config.mock.rss_bytes = Some(2 << 30);
+        config.budget.memory_bytes = Some(1 << 30);
+        config.budget.grace_secs = 0;
+        config.poll.workers_interval_secs = 1;
+    })
+    .await;
+    let id = harness.create_session("dave").await;
+
+    let (status, body) = harness.get(&format!("/status/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let session_status: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(session_status["usage"]["rss_bytes"], 2u64 << 30);
+
+    // Nothing invokes the poller here, the background driver has to
+    let mut body = String::new();
+    for _ in 0..50 {
+        body = harness.get(&format!("/session/{}", id)).await.1;
+        if body.contains("Session failed: memory_budget_exceeded") {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    panic!("worker was never killed: {body}");
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn sessions_take_warm_workers() {
+    let harness = Harness::start_with(|config| config.warm_pool.size = 1).await;
+    let replenished = harness
+        .client
+        .post(format!(
+            "{}/WorkerPoolService/pool/replenish_warm_pool",
+            harness.ingress_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert!(replenished.status().is_success(), "{:?}", replenished);
+    // The readiness check runs as its own delayed invocation
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    harness.create_session("erin").await;
+    let (_, metrics) = harness.get("/metrics").await;
+    assert!(
+        metrics.contains("orchestrator_warm_pool_hits_total 1"),
+        "{metrics}"
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn workers_are_reused_until_recycled() {
+    let harness = Harness::start_with(|config| config.recycle.max_sessions = 2).await;
+    let delete = |id: String| {
+        harness
+            .client
+            .delete(format!("{}/session/{}", harness.api_url, id))
+            .send()
+    };
+    let worker_of = async |id: &str| {
+        let (_, history) = harness.get(&format!("/session/{}/history", id)).await;
+        let history: Value = serde_json::from_str(&history).unwrap();
+        let events = history.as_array().unwrap();
+        let placed = events.iter().find(|e| e["kind"] == "session_placed");
+        placed.unwrap()["worker_id"].as_str().unwrap().to_string()
+    };
+
+    let first = harness.create_session("frank").await;
+    assert_eq!(
+        delete(first.clone()).await.unwrap().status(),
+        StatusCode::OK
+    );
+    let second = harness.create_session("frank").await;
+    assert_eq!(worker_of(&first).await, worker_of(&second).await);
+
+    // Its second session was its last
+    assert_eq!(
+        delete(second.clone()).await.unwrap().status(),
+        StatusCode::OK
+    );
+    let third = harness.create_session("frank").await;
+    assert_ne!(worker_of(&second).await, worker_of(&third).await);
+    // Its successor says whose place it took
+    let (_, history) = harness.get(&format!("/session/{}/history", third)).await;
+    let history: Value = serde_json::from_str(&history).unwrap();
+    let kinds: Vec<&str> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "session_created",
+            "worker_restarted",
+            "session_placed",
+            "session_ready"
+        ]
+    );
+    assert_eq!(
+        history[1]["reason"],
+        format!("replaces {}", worker_of(&second).await)
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn workers_share_their_slots_between_sessions() {
+    let harness = Harness::start_with(|config| {
+        config.worker.session_capacity = 2;
+        config.recycle.max_sessions = 0;
+    })
+    .await;
+    let worker_of = async |id: &str| {
+        let (_, history) = harness.get(&format!("/session/{}/history", id)).await;
+        let history: Value = serde_json::from_str(&history).unwrap();
+        let events = history.as_array().unwrap();
+        let placed = events.iter().find(|e| e["kind"] == "session_placed");
+        placed.unwrap()["worker_id"].as_str().unwrap().to_string()
+    };
+
+    let first = harness.create_session("grace").await;
+    let second = harness.create_session("grace").await;
+    let third = harness.create_session("grace").await;
+    assert_eq!(worker_of(&first).await, worker_of(&second).await);
+    assert_ne!(worker_of(&first).await, worker_of(&third).await);
+
+    let (_, body) = harness.get("/get_all_sessions").await;
+    for id in [&first, &second, &third] {
+        assert!(body.contains(id.as_str()), "{body}");
+    }
+
+    // Only the slot is freed, the worker keeps serving the other session
+    let deleted = harness
+        .client
+        .delete(format!("{}/session/{}", harness.api_url, first))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::OK);
+    let (status, body) = harness.get(&format!("/session/{}", second)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // Both workers have a slot free again, neither needs a new one
+    let fourth = harness.create_session("grace").await;
+    let worker = worker_of(&fourth).await;
+    assert!(worker == worker_of(&second).await || worker == worker_of(&third).await);
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn placement_decisions_are_recorded() {
+    let harness = Harness::start_with(|config| {
+        config.worker.session_capacity = 2;
+        config.recycle.max_sessions = 0;
+        config.scheduler.strategy = Strategy::LeastLoaded;
+        config
+            .scheduler
+            .worker_labels
+            .insert("region".to_string(), "eu".to_string());
+    })
+    .await;
+    let first = harness.create_session("heidi").await;
+    let (_, history) = harness.get(&format!("/session/{}/history", first)).await;
+    let history: Value = serde_json::from_str(&history).unwrap();
+    assert_eq!(history[1]["kind"], "session_placed");
+    assert!(
+        history[1]["reason"]
+            .as_str()
+            .unwrap()
+            .starts_with("least_loaded: none of 0 workers fit"),
+        "{history}"
+    );
+
+    let unschedulable = harness
+        .client
+        .post(format!("{}/session", harness.api_url))
+        .json(&serde_json::json!({ "user": "heidi", "labels": { "region": "us" } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unschedulable.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let invalid = harness
+        .client
+        .post(format!("{}/session", harness.api_url))
+        .json(&serde_json::json!({ "user": "heidi", "resources": { "cpus": -1.0 } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[ignore = "needs restate-server"]
+async fn idle_workers_are_retired_by_age() {
+    let harness = Harness::start_with(|config| {
+        config.recycle.max_sessions = 0;
+        config.recycle.max_age_secs = Some(1);
+        config.poll.workers_interval_secs = 1;
+    })
+    .await;
+    let id = harness.create_session("ivan").await;
+    let deleted = harness
+        .client
+        .delete(format!("{}/session/{}", harness.api_url, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::OK);
+
+    // The idle worker is only retired by the poller
+    let mut metrics = String::new();
+    for _ in 0..50 {
+        metrics = harness.get("/metrics").await.1;
+        if metrics.contains("orchestrator_worker_retirements_total{reason=\"max_age\"}") {
+            // Recorded without a session, it still shows in the history of the ones it served
+            let (_, history) = harness.get(&format!("/session/{}/history", id)).await;
+            assert!(history.contains("worker_retired"), "{history}");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    panic!("worker was never retired: {metrics}");
+}